@@ -0,0 +1,47 @@
+use crate::{CheckOutcome, HealthStatus};
+
+/// Apdex bucket of a single check against a target latency T.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApdexSample {
+    /// Up with latency <= T
+    Satisfied,
+    /// Up with T < latency <= 4T
+    Tolerating,
+    /// Slower than 4T, or down
+    Frustrated,
+}
+
+impl ApdexSample {
+    pub fn classify(latency_ms: Option<u128>, target_ms: u64) -> Self {
+        let target = target_ms as u128;
+        match latency_ms {
+            Some(l) if l <= target => ApdexSample::Satisfied,
+            Some(l) if l <= target.saturating_mul(4) => ApdexSample::Tolerating,
+            _ => ApdexSample::Frustrated,
+        }
+    }
+
+    pub fn from_outcome(outcome: &CheckOutcome, target_ms: u64) -> Self {
+        match outcome.status {
+            HealthStatus::Up => Self::classify(outcome.latency_ms, target_ms),
+            HealthStatus::Degraded(_) | HealthStatus::Down(_) => ApdexSample::Frustrated,
+        }
+    }
+}
+
+/// Apdex score `(satisfied + tolerating / 2) / total`, or `None` without samples.
+pub fn apdex_score<'a>(samples: impl IntoIterator<Item = &'a ApdexSample>) -> Option<f64> {
+    let (mut satisfied, mut tolerating, mut total) = (0u64, 0u64, 0u64);
+    for sample in samples {
+        total += 1;
+        match sample {
+            ApdexSample::Satisfied => satisfied += 1,
+            ApdexSample::Tolerating => tolerating += 1,
+            ApdexSample::Frustrated => {}
+        }
+    }
+    if total == 0 {
+        return None;
+    }
+    Some((satisfied as f64 + tolerating as f64 / 2.0) / total as f64)
+}
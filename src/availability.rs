@@ -0,0 +1,48 @@
+use crate::{CheckOutcome, HealthStatus, RollingWindow};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// Per-endpoint availability over a rolling window of watch iterations.
+///
+/// Time is passed in as `now` rather than read from the clock, so windows can be simulated.
+#[derive(Debug, Clone)]
+pub struct AvailabilityTracker {
+    window: Duration,
+    /// Whether each check in the window was up, by endpoint
+    checks: HashMap<String, RollingWindow<bool>>,
+}
+
+impl AvailabilityTracker {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            checks: HashMap::new(),
+        }
+    }
+
+    /// Records a sweep's outcomes as of `now`.
+    pub fn record(&mut self, now: Instant, outcomes: &[CheckOutcome]) {
+        for outcome in outcomes {
+            self.checks
+                .entry(outcome.endpoint.clone())
+                .or_insert_with(|| RollingWindow::new(self.window))
+                .push(now, matches!(outcome.status, HealthStatus::Up));
+        }
+    }
+
+    /// Share of up checks (0.0 to 1.0) per endpoint in the window ending at `now`; endpoints
+    /// without checks in the window are forgotten.
+    pub fn ratios(&mut self, now: Instant) -> BTreeMap<String, f64> {
+        self.checks.retain(|_, window| {
+            window.prune(now);
+            !window.is_empty()
+        });
+        self.checks
+            .iter()
+            .map(|(endpoint, window)| {
+                let up = window.iter().filter(|&&up| up).count();
+                (endpoint.clone(), up as f64 / window.len() as f64)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Byte budget shared by all concurrent body reads (`max_inflight_body_bytes`).
+///
+/// Before sending its request a check reserves its body limit from a byte-counting
+/// semaphore, trims the reservation to the announced `Content-Length`, and holds it until
+/// the body has been checked, so the buffered bodies of all
+/// in-flight checks stay within the budget however high `concurrency` is. A reservation
+/// larger than the whole budget is clamped to it, so such a body is read alone rather than
+/// never.
+#[derive(Debug, Clone)]
+pub struct BodyBudget {
+    semaphore: Arc<Semaphore>,
+    capacity: u32,
+}
+
+impl BodyBudget {
+    pub fn new(max_bytes: usize) -> Self {
+        let capacity = max_bytes.clamp(1, Semaphore::MAX_PERMITS.min(u32::MAX as usize)) as u32;
+        Self {
+            semaphore: Arc::new(Semaphore::new(capacity as usize)),
+            capacity,
+        }
+    }
+
+    /// Waits until `bytes` (at most the whole budget) are free and reserves them.
+    pub async fn reserve(&self, bytes: usize) -> OwnedSemaphorePermit {
+        let bytes = bytes.clamp(1, self.capacity as usize) as u32;
+        Arc::clone(&self.semaphore)
+            .acquire_many_owned(bytes)
+            .await
+            .expect("body budget semaphore is never closed")
+    }
+
+    /// Bytes currently free.
+    pub fn available(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+}
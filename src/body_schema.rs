@@ -0,0 +1,52 @@
+use anyhow::{Context, Result, anyhow};
+use jsonschema::Validator;
+use serde_json::Value;
+use std::fs;
+use std::sync::Arc;
+
+/// Validation errors listed in a down reason; the rest are counted.
+const MAX_REPORTED_ERRORS: usize = 3;
+
+/// JSON Schema loaded from `expected_body_schema`, compiled once and shared by clones.
+#[derive(Debug, Clone)]
+pub struct BodySchema(Arc<Validator>);
+
+impl BodySchema {
+    /// Reads and compiles the schema at `path`.
+    pub fn from_file(path: &str) -> Result<Self> {
+        let raw = fs::read_to_string(path).with_context(|| format!("failed to read {}", path))?;
+        let schema: Value =
+            serde_json::from_str(&raw).with_context(|| format!("{} is not valid JSON", path))?;
+        let validator = jsonschema::validator_for(&schema)
+            .map_err(|e| anyhow!("{} is not a valid JSON Schema: {}", path, e))?;
+        Ok(Self(Arc::new(validator)))
+    }
+
+    /// Validates a response body; `Err` carries the down reason.
+    pub fn check(&self, body: &[u8]) -> Result<(), String> {
+        let instance: Value = serde_json::from_slice(body)
+            .map_err(|e| format!("body is not JSON, cannot validate against schema ({})", e))?;
+        let errors: Vec<String> = self
+            .0
+            .iter_errors(&instance)
+            .map(|e| {
+                let path = e.instance_path().to_string();
+                format!("{}: {}", if path.is_empty() { "/" } else { &path }, e)
+            })
+            .collect();
+        if errors.is_empty() {
+            return Ok(());
+        }
+        let mut reason = format!(
+            "body does not match schema: {}",
+            errors[..errors.len().min(MAX_REPORTED_ERRORS)].join("; ")
+        );
+        if errors.len() > MAX_REPORTED_ERRORS {
+            reason.push_str(&format!(
+                " (and {} more)",
+                errors.len() - MAX_REPORTED_ERRORS
+            ));
+        }
+        Err(reason)
+    }
+}
@@ -0,0 +1,104 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+use x509_parser::prelude::{FromDer, X509Certificate};
+use x509_parser::public_key::PublicKey;
+
+/// Minimum requirements for the certificate served by an HTTPS endpoint.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct CertRequirements {
+    /// Minimum RSA modulus size in bits
+    #[serde(default = "default_min_rsa_bits")]
+    pub min_rsa_bits: usize,
+    /// Accept elliptic-curve keys (ECDSA, Ed25519 and Ed448)
+    #[serde(default = "default_true")]
+    pub allow_ec: bool,
+    /// Reject SHA-1 (and MD5) signatures
+    #[serde(default = "default_true")]
+    pub reject_sha1: bool,
+}
+
+impl Default for CertRequirements {
+    fn default() -> Self {
+        Self {
+            min_rsa_bits: default_min_rsa_bits(),
+            allow_ec: true,
+            reject_sha1: true,
+        }
+    }
+}
+
+fn default_min_rsa_bits() -> usize {
+    2048
+}
+fn default_true() -> bool {
+    true
+}
+
+/// Signature algorithm OIDs using SHA-1 or MD5.
+const WEAK_SIGNATURE_OIDS: &[(&str, &str)] = &[
+    ("1.2.840.113549.1.1.4", "md5WithRSAEncryption"),
+    ("1.2.840.113549.1.1.5", "sha1WithRSAEncryption"),
+    ("1.3.14.3.2.29", "sha1WithRSAEncryption"),
+    ("1.2.840.10045.4.1", "ecdsa-with-SHA1"),
+    ("1.2.840.10040.4.3", "dsa-with-SHA1"),
+];
+
+/// Ed25519 and Ed448 key OIDs. These fixed-size curves meet any minimum strength, but
+/// x509-parser reports them as unknown key types.
+const EDDSA_KEY_OIDS: &[&str] = &["1.3.101.112", "1.3.101.113"];
+
+/// Checks a DER-encoded leaf certificate against `req`; `Err` carries the down reason.
+///
+/// Besides the configured key and signature rules, the validity window must be well-formed
+/// and include the current time.
+pub fn check_certificate(der: &[u8], req: &CertRequirements) -> Result<(), String> {
+    let violation = |detail: String| format!("certificate does not meet policy: {}", detail);
+    let (_, cert) = X509Certificate::from_der(der)
+        .map_err(|e| violation(format!("unparseable certificate ({})", e)))?;
+    let eddsa = EDDSA_KEY_OIDS.contains(
+        &cert
+            .public_key()
+            .algorithm
+            .algorithm
+            .to_id_string()
+            .as_str(),
+    );
+    match cert.public_key().parsed() {
+        Ok(PublicKey::RSA(rsa)) => {
+            let bits = rsa.key_size();
+            if bits < req.min_rsa_bits {
+                return Err(violation(format!("RSA {} < {}", bits, req.min_rsa_bits)));
+            }
+        }
+        Ok(PublicKey::EC(_)) if req.allow_ec => {}
+        Ok(PublicKey::EC(_)) => return Err(violation("EC keys are not allowed".to_string())),
+        _ if eddsa && req.allow_ec => {}
+        _ if eddsa => return Err(violation("EC keys are not allowed".to_string())),
+        _ => return Err(violation("unsupported public key type".to_string())),
+    }
+    if req.reject_sha1 {
+        let oid = cert.signature_algorithm.algorithm.to_id_string();
+        if let Some((_, name)) = WEAK_SIGNATURE_OIDS.iter().find(|(weak, _)| *weak == oid) {
+            return Err(violation(format!("weak signature algorithm {}", name)));
+        }
+    }
+    let validity = cert.validity();
+    let (not_before, not_after) = (
+        validity.not_before.timestamp(),
+        validity.not_after.timestamp(),
+    );
+    if not_before >= not_after {
+        return Err(violation("notBefore is not before notAfter".to_string()));
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    if now < not_before {
+        return Err(violation("not yet valid".to_string()));
+    }
+    if now > not_after {
+        return Err(violation("expired".to_string()));
+    }
+    Ok(())
+}
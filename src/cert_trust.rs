@@ -0,0 +1,116 @@
+use crate::{Config, ca_bundle_dir_files};
+use anyhow::{Context, Result};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName};
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore, crypto::ring};
+use tracing::debug;
+use url::Url;
+
+/// Whether an endpoint's certificate chain validates against the trusted roots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertTrust {
+    Trusted,
+    /// Carries the validation error, e.g. `invalid peer certificate: UnknownIssuer`
+    Untrusted(String),
+}
+
+/// Validates certificate chains for `report_cert_validation`, independently of
+/// `danger_accept_invalid_certs`.
+///
+/// Trusts the same roots as the check clients: the bundled web PKI roots plus the CA bundles.
+#[derive(Clone)]
+pub struct TrustAudit {
+    connector: TlsConnector,
+    /// The check clients skip validation, so every audit needs its own handshake
+    accepts_invalid: bool,
+    /// `fail_on_untrusted_cert`
+    pub(crate) fatal: bool,
+}
+
+impl fmt::Debug for TrustAudit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrustAudit")
+            .field("accepts_invalid", &self.accepts_invalid)
+            .field("fatal", &self.fatal)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TrustAudit {
+    pub(crate) fn new(cfg: &Config) -> Result<Self> {
+        let mut roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let mut ca_files: Vec<PathBuf> = cfg.ca_bundle_path.iter().map(PathBuf::from).collect();
+        if let Some(dir) = &cfg.ca_bundle_dir {
+            ca_files.extend(ca_bundle_dir_files(dir)?);
+        }
+        for path in &ca_files {
+            for cert in CertificateDer::pem_file_iter(path)
+                .with_context(|| format!("failed to read ca bundle at {}", path.display()))?
+            {
+                let cert =
+                    cert.with_context(|| format!("invalid PEM in ca bundle {}", path.display()))?;
+                roots
+                    .add(cert)
+                    .with_context(|| format!("invalid certificate in {}", path.display()))?;
+            }
+        }
+        let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .context("TLS protocol versions")?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok(Self {
+            connector: TlsConnector::from(Arc::new(config)),
+            accepts_invalid: cfg.danger_accept_invalid_certs,
+            fatal: cfg.fail_on_untrusted_cert,
+        })
+    }
+
+    /// Audits the certificate of the https `target`, verified against `sni` if set.
+    ///
+    /// A response over a validating client already proves the chain trusted; otherwise a
+    /// separate TLS handshake decides. `None` if the handshake fails for another reason than
+    /// the certificate (e.g. the host is unreachable).
+    pub(crate) async fn check(
+        &self,
+        target: &str,
+        sni: Option<&str>,
+        responded: bool,
+        timeout: Duration,
+    ) -> Option<CertTrust> {
+        let url = Url::parse(target).ok()?;
+        if url.scheme() != "https" {
+            return None;
+        }
+        if responded && !self.accepts_invalid {
+            return Some(CertTrust::Trusted);
+        }
+        let host = url.host_str()?.trim_matches(['[', ']']).to_string();
+        let port = url.port_or_known_default()?;
+        let name = ServerName::try_from(sni.unwrap_or(&host).to_string()).ok()?;
+        let handshake = async {
+            let stream = tokio::net::TcpStream::connect((host.as_str(), port)).await?;
+            self.connector.connect(name, stream).await
+        };
+        match tokio::time::timeout(timeout, handshake).await {
+            Ok(Ok(_)) => Some(CertTrust::Trusted),
+            Ok(Err(e)) => match e.get_ref().and_then(|e| e.downcast_ref::<rustls::Error>()) {
+                Some(err @ rustls::Error::InvalidCertificate(_)) => {
+                    Some(CertTrust::Untrusted(err.to_string()))
+                }
+                _ => {
+                    debug!(target = %host, error = %e, "certificate audit handshake failed");
+                    None
+                }
+            },
+            Err(_) => None,
+        }
+    }
+}
@@ -0,0 +1,55 @@
+/// AIMD controller for the number of in-flight checks in adaptive mode.
+///
+/// After each sweep the p95 latency is compared with a smoothed baseline: while it stays
+/// within `degrade_ratio` of the baseline concurrency grows by one, otherwise it halves.
+/// The baseline only tracks healthy sweeps, so an overloaded sweep does not raise it.
+#[derive(Debug, Clone)]
+pub struct AdaptiveConcurrency {
+    current: usize,
+    max: usize,
+    baseline_p95_ms: Option<f64>,
+    degrade_ratio: f64,
+}
+
+impl AdaptiveConcurrency {
+    pub fn new(initial: usize, max: usize) -> Self {
+        let max = max.max(1);
+        Self {
+            current: initial.clamp(1, max),
+            max,
+            baseline_p95_ms: None,
+            degrade_ratio: 1.5,
+        }
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Feeds the p95 latency of the last sweep and returns the concurrency for the next one.
+    pub fn observe(&mut self, p95_ms: f64) -> usize {
+        match self.baseline_p95_ms {
+            Some(baseline) if p95_ms > baseline * self.degrade_ratio => {
+                self.current = (self.current / 2).max(1);
+            }
+            baseline => {
+                self.baseline_p95_ms = Some(match baseline {
+                    Some(b) => b * 0.8 + p95_ms * 0.2,
+                    None => p95_ms,
+                });
+                self.current = (self.current + 1).min(self.max);
+            }
+        }
+        self.current
+    }
+}
+
+/// Nearest-rank percentile (`p` in 0..=100) of `values`; `None` if empty.
+pub fn percentile(values: &mut [f64], p: f64) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let rank = ((p / 100.0) * values.len() as f64).ceil() as usize;
+    Some(values[rank.clamp(1, values.len()) - 1])
+}
@@ -0,0 +1,118 @@
+use crate::{EndpointConfig, ExpectedStatus, StatusCategories, StatusCategory};
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+
+/// Columns an `endpoints_csv` header row may name
+const COLUMNS: &[&str] = &[
+    "url",
+    "method",
+    "expected_status",
+    "timeout_ms",
+    "retries",
+    "priority",
+    "template",
+];
+
+/// Reads endpoints from a CSV file, or a TSV file if it ends in `.tsv`.
+///
+/// The header row names the columns (any of [`COLUMNS`], in any order; `url` is required).
+/// Empty cells leave the setting at its default, so a `template` column can supply the rest.
+pub(crate) fn load_endpoints_csv(path: &str) -> Result<Vec<EndpointConfig>> {
+    let tsv = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("tsv"));
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(if tsv { b'\t' } else { b',' })
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .with_context(|| format!("failed to read endpoints_csv {}", path))?;
+    let headers: Vec<String> = reader
+        .headers()
+        .with_context(|| format!("endpoints_csv {}: failed to read the header row", path))?
+        .iter()
+        .map(str::to_ascii_lowercase)
+        .collect();
+    if let Some(unknown) = headers.iter().find(|h| !COLUMNS.contains(&h.as_str())) {
+        bail!(
+            "endpoints_csv {}: unknown column `{}` (expected {})",
+            path,
+            unknown,
+            COLUMNS.join(", ")
+        );
+    }
+    if !headers.iter().any(|h| h == "url") {
+        bail!("endpoints_csv {}: missing required column `url`", path);
+    }
+
+    let mut endpoints = Vec::new();
+    for record in reader.records() {
+        let record = record.with_context(|| format!("endpoints_csv {}", path))?;
+        let line = record.position().map_or(0, |p| p.line());
+        let mut ep = EndpointConfig::default();
+        for (column, value) in headers.iter().zip(record.iter()) {
+            if value.is_empty() {
+                continue;
+            }
+            set_column(&mut ep, column, value)
+                .with_context(|| format!("endpoints_csv {} line {}", path, line))?;
+        }
+        if ep.url.is_empty() {
+            bail!("endpoints_csv {} line {}: `url` is empty", path, line);
+        }
+        endpoints.push(ep);
+    }
+    Ok(endpoints)
+}
+
+fn set_column(ep: &mut EndpointConfig, column: &str, value: &str) -> Result<()> {
+    let number = |value: &str| -> Result<u64> {
+        value
+            .parse()
+            .with_context(|| format!("`{}` must be a number, got `{}`", column, value))
+    };
+    match column {
+        "url" => ep.url = value.to_string(),
+        "method" => ep.method = value.to_ascii_uppercase(),
+        "expected_status" => ep.expected_status = Some(expected_status(value)?),
+        "timeout_ms" => ep.timeout_ms = Some(number(value)?),
+        "retries" => ep.retries = Some(number(value)?.try_into()?),
+        "priority" => {
+            ep.priority = value
+                .parse()
+                .with_context(|| format!("`priority` must be an integer, got `{}`", value))?
+        }
+        "template" => ep.template = Some(value.to_string()),
+        _ => unreachable!("columns are validated against COLUMNS"),
+    }
+    Ok(())
+}
+
+/// `200`, a range like `200-299` or a class like `2xx`.
+fn expected_status(value: &str) -> Result<ExpectedStatus> {
+    let invalid = || {
+        anyhow::anyhow!(
+            "invalid expected_status `{}` (expected e.g. 200, 200-299 or 2xx)",
+            value
+        )
+    };
+    if let Ok(category) = serde_json::from_value::<StatusCategory>(value.into()) {
+        return Ok(ExpectedStatus {
+            min: None,
+            max: None,
+            category: Some(StatusCategories::One(category)),
+            allowed: None,
+        });
+    }
+    let (min, max) = value.split_once('-').unwrap_or((value, value));
+    let min: u16 = min.trim().parse().map_err(|_| invalid())?;
+    let max: u16 = max.trim().parse().map_err(|_| invalid())?;
+    if min > max {
+        return Err(invalid());
+    }
+    Ok(ExpectedStatus {
+        min: Some(min),
+        max: Some(max),
+        category: None,
+        allowed: None,
+    })
+}
@@ -0,0 +1,153 @@
+use crate::{
+    CheckOutcome, DnsExpectation, DnsRecordType, EndpointConfig, HealthStatus, ReasonCode,
+};
+use anyhow::{Context, Result};
+use hickory_resolver::TokioResolver;
+use hickory_resolver::proto::rr::{RData, RecordType};
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Source of DNS answers for `kind: dns` checks.
+pub trait DnsResolver {
+    /// Resolves `host` and returns the record values of `record_type` (addresses or names).
+    fn lookup(
+        &self,
+        host: &str,
+        record_type: DnsRecordType,
+    ) -> impl Future<Output = Result<Vec<String>>> + Send;
+}
+
+/// Resolver using the system configuration (`/etc/resolv.conf`).
+pub struct SystemResolver(TokioResolver);
+
+impl SystemResolver {
+    pub fn new() -> Result<Self> {
+        let resolver = TokioResolver::builder_tokio()
+            .context("failed to read system DNS configuration")?
+            .build()
+            .context("failed to build DNS resolver")?;
+        Ok(Self(resolver))
+    }
+}
+
+impl DnsResolver for SystemResolver {
+    async fn lookup(&self, host: &str, record_type: DnsRecordType) -> Result<Vec<String>> {
+        let rtype = match record_type {
+            DnsRecordType::A => RecordType::A,
+            DnsRecordType::Aaaa => RecordType::AAAA,
+            DnsRecordType::Cname => RecordType::CNAME,
+        };
+        let lookup = self.0.lookup(host, rtype).await?;
+        // Answers may include the CNAME chain leading to the requested records
+        Ok(lookup
+            .answers()
+            .iter()
+            .filter_map(|record| match (record_type, &record.data) {
+                (DnsRecordType::A, RData::A(a)) => Some(a.to_string()),
+                (DnsRecordType::Aaaa, RData::AAAA(aaaa)) => Some(aaaa.to_string()),
+                (DnsRecordType::Cname, RData::CNAME(name)) => Some(name.to_string()),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+/// Shared system resolver, built on first use.
+pub(crate) fn system_resolver() -> Result<&'static SystemResolver, String> {
+    static RESOLVER: OnceLock<Result<SystemResolver, String>> = OnceLock::new();
+    RESOLVER
+        .get_or_init(|| SystemResolver::new().map_err(|e| format!("{:#}", e)))
+        .as_ref()
+        .map_err(Clone::clone)
+}
+
+/// Resolves the endpoint's host and compares the answers with `ep.dns`.
+///
+/// Latency is the resolution time. Values are compared as sets, case-insensitively and
+/// ignoring trailing dots, so record order and FQDN notation do not matter.
+pub async fn check_dns<R: DnsResolver>(
+    resolver: &R,
+    ep: &EndpointConfig,
+    default_timeout_ms: u64,
+) -> CheckOutcome {
+    let mut outcome = CheckOutcome::new(crate::redact_url(&ep.url), HealthStatus::Up);
+    let verdict = check_dns_target(resolver, ep, &ep.url, default_timeout_ms, &mut outcome).await;
+    crate::record_verdict(ep, outcome, verdict.map_err(crate::down))
+}
+
+pub(crate) async fn check_dns_target<R: DnsResolver>(
+    resolver: &R,
+    ep: &EndpointConfig,
+    target: &str,
+    default_timeout_ms: u64,
+    outcome: &mut CheckOutcome,
+) -> Result<(), (ReasonCode, String)> {
+    let Some(expect) = &ep.dns else {
+        return Err((
+            ReasonCode::ConfigError,
+            "dns check without a `dns` expectation".to_string(),
+        ));
+    };
+    let host = host_of(target);
+    let timeout = Duration::from_millis(ep.timeout_ms.unwrap_or(default_timeout_ms));
+    let start = Instant::now();
+    let answers =
+        match tokio::time::timeout(timeout, resolver.lookup(&host, expect.record_type)).await {
+            Ok(Ok(answers)) => answers,
+            Ok(Err(e)) => {
+                return Err((
+                    ReasonCode::DnsFailure,
+                    format!("DNS lookup of {} failed: {:#}", host, e),
+                ));
+            }
+            Err(_) => {
+                return Err((
+                    ReasonCode::Timeout,
+                    format!("DNS lookup of {} timed out", host),
+                ));
+            }
+        };
+    outcome.latency_ms = Some(start.elapsed().as_millis());
+    compare(expect, &answers).map_err(|reason| (ReasonCode::DnsMismatch, reason))
+}
+
+fn host_of(target: &str) -> String {
+    Url::parse(target)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.trim_matches(['[', ']']).to_string()))
+        .unwrap_or_else(|| target.to_string())
+}
+
+fn compare(expect: &DnsExpectation, answers: &[String]) -> Result<(), String> {
+    let got = normalize(answers);
+    let want = normalize(&expect.expected);
+    if got == want {
+        return Ok(());
+    }
+    let show = |set: &BTreeSet<String>| {
+        if set.is_empty() {
+            "nothing".to_string()
+        } else {
+            set.iter().cloned().collect::<Vec<_>>().join(", ")
+        }
+    };
+    Err(format!(
+        "resolved to {}, expected {}",
+        show(&got),
+        show(&want)
+    ))
+}
+
+fn normalize(values: &[String]) -> BTreeSet<String> {
+    values
+        .iter()
+        .map(|v| match v.parse::<IpAddr>() {
+            Ok(ip) => ip.to_string(),
+            Err(_) => v.trim_end_matches('.').to_ascii_lowercase(),
+        })
+        .collect()
+}
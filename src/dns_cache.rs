@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Last successful resolution per host, used to ride out transient resolver failures.
+///
+/// When a lookup fails but the host resolved within `tolerance`, the cached addresses are
+/// returned instead, so the request is pinned to the last known IPs and only fails if those
+/// are unreachable too.
+#[derive(Debug)]
+pub struct DnsCache {
+    tolerance: Duration,
+    entries: Mutex<HashMap<String, (Instant, Vec<SocketAddr>)>>,
+}
+
+impl DnsCache {
+    pub fn new(tolerance: Duration) -> Self {
+        Self {
+            tolerance,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Resolves `host` with `lookup`, falling back to a recent cached answer on failure.
+    pub async fn resolve<F, E>(&self, host: &str, lookup: F) -> Result<Vec<SocketAddr>, E>
+    where
+        F: Future<Output = Result<Vec<SocketAddr>, E>>,
+        E: Display,
+    {
+        let result = lookup.await;
+        let mut entries = self.entries.lock().expect("dns cache lock poisoned");
+        match result {
+            Ok(addrs) => {
+                if !addrs.is_empty() {
+                    entries.insert(host.to_string(), (Instant::now(), addrs.clone()));
+                }
+                Ok(addrs)
+            }
+            Err(e) => match entries.get(host) {
+                Some((resolved_at, addrs)) if resolved_at.elapsed() <= self.tolerance => {
+                    warn!(
+                        host,
+                        age_sec = resolved_at.elapsed().as_secs(),
+                        error = %e,
+                        "DNS lookup failed; using last resolved addresses"
+                    );
+                    Ok(addrs.clone())
+                }
+                _ => Err(e),
+            },
+        }
+    }
+}
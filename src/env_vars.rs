@@ -0,0 +1,88 @@
+use anyhow::{Result, bail};
+use serde_json::Value;
+
+/// Expands `${VAR}` and `${VAR:-default}` in every string of a parsed config document.
+///
+/// The default applies when the variable is unset or empty; `$${` is a literal `${`.
+/// A variable that is unset and has no default is an error naming the config value.
+pub(crate) fn interpolate_env(doc: &mut Value) -> Result<()> {
+    interpolate_value(doc, &mut String::new())
+}
+
+fn interpolate_value(value: &mut Value, path: &mut String) -> Result<()> {
+    match value {
+        Value::String(s) if s.contains("${") => {
+            *s = interpolate_str(s, |name| std::env::var(name).ok())
+                .map_err(|e| anyhow::anyhow!("config value `{}`: {}", path, e))?;
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{}]", i));
+                interpolate_value(item, path)?;
+                path.truncate(len);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                let len = path.len();
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(key);
+                interpolate_value(item, path)?;
+                path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn interpolate_str(input: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            // `$${` escapes the placeholder
+            out.push_str(&rest[..start - 1]);
+            out.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            bail!("unclosed `${{` in {:?}", input);
+        };
+        let inner = &rest[start + 2..start + len];
+        let (name, default) = match inner.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (inner, None),
+        };
+        if !is_var_name(name) {
+            bail!("invalid environment variable name `{}`", name);
+        }
+        match (
+            lookup(name).filter(|v| !v.is_empty() || default.is_none()),
+            default,
+        ) {
+            (Some(value), _) => out.push_str(&value),
+            (None, Some(default)) => out.push_str(default),
+            (None, None) => bail!(
+                "environment variable `{}` is not set and has no default",
+                name
+            ),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn is_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
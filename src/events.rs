@@ -0,0 +1,305 @@
+use crate::{CheckOutcome, EventSink};
+use anyhow::{Result, bail};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Events held while the broker is slow or unavailable; newer events are dropped beyond this.
+const EVENT_BUFFER: usize = 1024;
+/// Attempts per event before it is dropped.
+const ATTEMPTS: u32 = 3;
+const RETRY_BASE: Duration = Duration::from_millis(200);
+/// How long [`EventPublisher::close`] waits for buffered events to be delivered.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Publishes each check outcome as JSON to the configured message bus.
+///
+/// Publishing never blocks checks: events go through a bounded buffer drained by a background
+/// task, which retries briefly and then drops the event with a warning. When the buffer is
+/// full (broker down for a while), new events are dropped.
+#[derive(Debug)]
+pub struct EventPublisher {
+    tx: mpsc::Sender<Event>,
+    task: JoinHandle<()>,
+    dropped: Arc<AtomicU64>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(not(any(feature = "nats", feature = "kafka")), allow(dead_code))]
+struct Event {
+    /// Endpoint, used as the message key where the bus supports one
+    key: String,
+    payload: Vec<u8>,
+}
+
+impl EventPublisher {
+    /// Validates the sink and starts the background publisher.
+    pub fn spawn(sink: &EventSink) -> Result<Self> {
+        let mut transport = Transport::new(sink)?;
+        let (tx, mut rx) = mpsc::channel::<Event>(EVENT_BUFFER);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let task_dropped = Arc::clone(&dropped);
+        let task = tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                let mut attempt = 1;
+                loop {
+                    match transport.send(&event).await {
+                        Ok(()) => break,
+                        Err(e) if attempt >= ATTEMPTS => {
+                            task_dropped.fetch_add(1, Ordering::Relaxed);
+                            warn!(endpoint = %event.key, error = %e, "dropping check event");
+                            break;
+                        }
+                        Err(e) => {
+                            debug!(attempt, error = %e, "retrying check event");
+                            tokio::time::sleep(RETRY_BASE * 2u32.pow(attempt - 1)).await;
+                            attempt += 1;
+                        }
+                    }
+                }
+            }
+        });
+        Ok(Self { tx, task, dropped })
+    }
+
+    /// Queues `outcome` for publishing without waiting for the broker.
+    pub fn publish(&self, outcome: &CheckOutcome) {
+        let mut json = outcome.to_json();
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        json["timestamp_ms"] = timestamp_ms.into();
+        let event = Event {
+            key: outcome.endpoint.clone(),
+            payload: json.to_string().into_bytes(),
+        };
+        if self.tx.try_send(event).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            warn!(endpoint = %outcome.endpoint, "event buffer full; dropping check event");
+        }
+    }
+
+    /// Number of events dropped so far.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Stops accepting events and waits briefly for buffered ones to be delivered.
+    pub async fn close(self) {
+        drop(self.tx);
+        let mut task = self.task;
+        if tokio::time::timeout(CLOSE_TIMEOUT, &mut task)
+            .await
+            .is_err()
+        {
+            warn!("event sink did not drain in time; dropping buffered events");
+            task.abort();
+        }
+    }
+}
+
+enum Transport {
+    #[cfg(feature = "nats")]
+    Nats(nats::Publisher),
+    #[cfg(feature = "kafka")]
+    Kafka(kafka::RestProducer),
+    /// Placeholder so the type compiles without any sink feature; never constructed
+    #[cfg(not(any(feature = "nats", feature = "kafka")))]
+    #[allow(dead_code)]
+    Unsupported(std::convert::Infallible),
+}
+
+impl Transport {
+    fn new(sink: &EventSink) -> Result<Self> {
+        match sink {
+            #[cfg(feature = "nats")]
+            EventSink::Nats {
+                url,
+                subject,
+                token,
+            } => Ok(Self::Nats(nats::Publisher::new(
+                url,
+                subject,
+                token.clone(),
+            )?)),
+            #[cfg(feature = "kafka")]
+            EventSink::Kafka { rest_url, topic } => {
+                Ok(Self::Kafka(kafka::RestProducer::new(rest_url, topic)?))
+            }
+            #[allow(unreachable_patterns)]
+            _ => bail!(
+                "event_sink `{}` requires building with the `{}` feature",
+                sink.kind(),
+                sink.kind()
+            ),
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "nats", feature = "kafka")), allow(unused_variables))]
+    async fn send(&mut self, event: &Event) -> Result<()> {
+        match self {
+            #[cfg(feature = "nats")]
+            Self::Nats(publisher) => publisher.publish(&event.payload).await,
+            #[cfg(feature = "kafka")]
+            Self::Kafka(producer) => producer.produce(&event.key, &event.payload).await,
+            #[cfg(not(any(feature = "nats", feature = "kafka")))]
+            Self::Unsupported(never) => match *never {},
+        }
+    }
+}
+
+#[cfg(feature = "nats")]
+mod nats {
+    use anyhow::{Context, Result, bail};
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpStream;
+    use url::Url;
+
+    const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Minimal NATS core-protocol publisher (plain TCP, optional token auth).
+    ///
+    /// Each publish is followed by a PING so broker errors surface as failures; the
+    /// connection is re-established after any error.
+    pub(super) struct Publisher {
+        addr: String,
+        subject: String,
+        token: Option<String>,
+        conn: Option<BufReader<TcpStream>>,
+    }
+
+    impl Publisher {
+        pub(super) fn new(url: &str, subject: &str, token: Option<String>) -> Result<Self> {
+            let parsed = Url::parse(url).with_context(|| format!("invalid NATS url {}", url))?;
+            if parsed.scheme() != "nats" {
+                bail!("NATS url must use nats:// (TLS is not supported): {}", url);
+            }
+            let host = parsed.host_str().context("NATS url has no host")?;
+            if subject.is_empty() || subject.contains(char::is_whitespace) {
+                bail!("invalid NATS subject `{}`", subject);
+            }
+            Ok(Self {
+                addr: format!("{}:{}", host, parsed.port().unwrap_or(4222)),
+                subject: subject.to_string(),
+                token,
+                conn: None,
+            })
+        }
+
+        pub(super) async fn publish(&mut self, payload: &[u8]) -> Result<()> {
+            let result = tokio::time::timeout(IO_TIMEOUT, self.try_publish(payload))
+                .await
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("NATS publish timed out")));
+            if result.is_err() {
+                self.conn = None;
+            }
+            result
+        }
+
+        async fn try_publish(&mut self, payload: &[u8]) -> Result<()> {
+            if self.conn.is_none() {
+                self.conn = Some(self.connect().await?);
+            }
+            let conn = self.conn.as_mut().expect("connected");
+            let mut frame = format!("PUB {} {}\r\n", self.subject, payload.len()).into_bytes();
+            frame.extend_from_slice(payload);
+            frame.extend_from_slice(b"\r\nPING\r\n");
+            conn.get_mut().write_all(&frame).await?;
+            await_pong(conn).await
+        }
+
+        async fn connect(&self) -> Result<BufReader<TcpStream>> {
+            let stream = TcpStream::connect(&self.addr)
+                .await
+                .with_context(|| format!("connecting to NATS at {}", self.addr))?;
+            let mut conn = BufReader::new(stream);
+            let mut info = String::new();
+            conn.read_line(&mut info).await?;
+            if !info.starts_with("INFO ") {
+                bail!("unexpected NATS greeting: {}", info.trim_end());
+            }
+            let mut options = serde_json::json!({
+                "verbose": false,
+                "pedantic": false,
+                "name": "rust-healthcheck",
+                "lang": "rust",
+                "version": env!("CARGO_PKG_VERSION"),
+            });
+            if let Some(token) = &self.token {
+                options["auth_token"] = token.clone().into();
+            }
+            let connect = format!("CONNECT {}\r\nPING\r\n", options);
+            conn.get_mut().write_all(connect.as_bytes()).await?;
+            await_pong(&mut conn).await?;
+            Ok(conn)
+        }
+    }
+
+    /// Reads until the server's PONG, answering its PINGs and failing on `-ERR`.
+    async fn await_pong(conn: &mut BufReader<TcpStream>) -> Result<()> {
+        loop {
+            let mut line = String::new();
+            if conn.read_line(&mut line).await? == 0 {
+                bail!("NATS connection closed");
+            }
+            match line.trim_end() {
+                "PONG" => return Ok(()),
+                "PING" => conn.get_mut().write_all(b"PONG\r\n").await?,
+                err if err.starts_with("-ERR") => bail!("NATS error: {}", err),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(feature = "kafka")]
+mod kafka {
+    use anyhow::{Context, Result, bail};
+    use reqwest::Client;
+    use serde_json::{Value, json};
+    use std::time::Duration;
+
+    /// Produces records through a Kafka REST Proxy (v2 JSON embedded format).
+    pub(super) struct RestProducer {
+        client: Client,
+        url: String,
+    }
+
+    impl RestProducer {
+        pub(super) fn new(rest_url: &str, topic: &str) -> Result<Self> {
+            if topic.is_empty() {
+                bail!("Kafka topic must not be empty");
+            }
+            let client = Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .context("building Kafka REST client")?;
+            Ok(Self {
+                client,
+                url: format!("{}/topics/{}", rest_url.trim_end_matches('/'), topic),
+            })
+        }
+
+        pub(super) async fn produce(&self, key: &str, payload: &[u8]) -> Result<()> {
+            let value: Value = serde_json::from_slice(payload)?;
+            let resp = self
+                .client
+                .post(&self.url)
+                .header("Content-Type", "application/vnd.kafka.json.v2+json")
+                .body(json!({ "records": [{ "key": key, "value": value }] }).to_string())
+                .send()
+                .await
+                .context("Kafka REST request failed")?;
+            let status = resp.status();
+            if !status.is_success() {
+                let body = resp.text().await.unwrap_or_default();
+                bail!("Kafka REST proxy answered {}: {}", status, body);
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::EndpointConfig;
+use anyhow::{Result, bail};
+
+/// Endpoints all `expand` entries together may produce
+pub(crate) const MAX_EXPANDED_ENDPOINTS: usize = 1000;
+
+/// Replaces every endpoint with `expand: true` by one endpoint per expansion of its URL.
+///
+/// `{1..20}` expands to a numeric range (zero-padded when the bounds are, as in `{01..20}`)
+/// and `{a,b,c}` to a list; several groups expand to every combination. Expanded endpoints
+/// keep all other settings of the original.
+pub(crate) fn expand_endpoints(endpoints: Vec<EndpointConfig>) -> Result<Vec<EndpointConfig>> {
+    let mut expanded_total = 0;
+    let mut out = Vec::with_capacity(endpoints.len());
+    for ep in endpoints {
+        if !ep.expand {
+            out.push(ep);
+            continue;
+        }
+        let urls = expand(&ep.url, MAX_EXPANDED_ENDPOINTS - expanded_total)?;
+        expanded_total += urls.len();
+        out.extend(urls.into_iter().map(|url| EndpointConfig {
+            url,
+            expand: false,
+            ..ep.clone()
+        }));
+    }
+    Ok(out)
+}
+
+/// Brace expansion of `pattern`, failing if it would produce more than `limit` strings.
+fn expand(pattern: &str, limit: usize) -> Result<Vec<String>> {
+    let mut results = vec![String::new()];
+    let mut rest = pattern;
+    while let Some(open) = rest.find('{') {
+        let Some(len) = rest[open..].find('}') else {
+            bail!("endpoint {}: unclosed `{{` in expand pattern", pattern);
+        };
+        let group = &rest[open + 1..open + len];
+        let alternatives =
+            alternatives(group).map_err(|e| anyhow::anyhow!("endpoint {}: {}", pattern, e))?;
+        if results.len() * alternatives.len() > limit {
+            bail!(
+                "endpoint {}: expansion exceeds the limit of {} endpoints",
+                pattern,
+                MAX_EXPANDED_ENDPOINTS
+            );
+        }
+        let prefix = &rest[..open];
+        results = results
+            .iter()
+            .flat_map(|head| {
+                alternatives
+                    .iter()
+                    .map(move |alt| format!("{}{}{}", head, prefix, alt))
+            })
+            .collect();
+        rest = &rest[open + len + 1..];
+    }
+    if results.len() == 1 && rest.len() == pattern.len() {
+        bail!(
+            "endpoint {}: `expand` is set but the URL has no `{{1..n}}` or `{{a,b}}` group",
+            pattern
+        );
+    }
+    for url in &mut results {
+        url.push_str(rest);
+    }
+    Ok(results)
+}
+
+/// The strings a single `{...}` group stands for.
+fn alternatives(group: &str) -> Result<Vec<String>, String> {
+    if let Some((from, to)) = group.split_once("..") {
+        let (Ok(start), Ok(end)) = (from.parse::<u64>(), to.parse::<u64>()) else {
+            return Err(format!("invalid range {{{}}}", group));
+        };
+        if start > end {
+            return Err(format!("range {{{}}} is descending", group));
+        }
+        if end - start >= MAX_EXPANDED_ENDPOINTS as u64 {
+            return Err(format!(
+                "range {{{}}} exceeds the limit of {} endpoints",
+                group, MAX_EXPANDED_ENDPOINTS
+            ));
+        }
+        let width = if from.starts_with('0') || to.starts_with('0') {
+            from.len().max(to.len())
+        } else {
+            0
+        };
+        return Ok((start..=end).map(|n| format!("{:0width$}", n)).collect());
+    }
+    if group.contains(',') {
+        return Ok(group.split(',').map(str::to_string).collect());
+    }
+    Err(format!(
+        "unsupported group {{{}}}, expected a range like {{1..20}} or a list like {{a,b,c}}",
+        group
+    ))
+}
@@ -0,0 +1,198 @@
+use anyhow::{Result, bail};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A metric a Prometheus text-format body (e.g. `/metrics`) must expose.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct ExpectMetric {
+    /// Metric name, e.g. `up` or `process_start_time_seconds`
+    pub name: String,
+    /// Labels a sample must carry (other labels are ignored)
+    #[serde(default)]
+    pub labels: Option<HashMap<String, String>>,
+    /// Comparison with `value`; without one the metric only has to be present
+    #[serde(default)]
+    pub op: Option<MetricOp>,
+    #[serde(default)]
+    pub value: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+pub enum MetricOp {
+    #[serde(rename = "==")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+}
+
+impl MetricOp {
+    fn holds(self, actual: f64, expected: f64) -> bool {
+        match self {
+            MetricOp::Eq => actual == expected,
+            MetricOp::Ne => actual != expected,
+            MetricOp::Gt => actual > expected,
+            MetricOp::Ge => actual >= expected,
+            MetricOp::Lt => actual < expected,
+            MetricOp::Le => actual <= expected,
+        }
+    }
+}
+
+impl fmt::Display for MetricOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MetricOp::Eq => "==",
+            MetricOp::Ne => "!=",
+            MetricOp::Gt => ">",
+            MetricOp::Ge => ">=",
+            MetricOp::Lt => "<",
+            MetricOp::Le => "<=",
+        })
+    }
+}
+
+impl ExpectMetric {
+    /// Checks the expectation when the config is loaded.
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            bail!("expect_metric needs a `name`");
+        }
+        match (self.op, self.value) {
+            (Some(_), None) => bail!("expect_metric `op` needs a `value`"),
+            (None, Some(_)) => bail!("expect_metric `value` needs an `op`"),
+            _ => Ok(()),
+        }
+    }
+
+    /// Evaluates the expectation against an exposition body; `Err` carries the down reason.
+    ///
+    /// Passes if any sample of the metric with the required labels satisfies the comparison.
+    pub(crate) fn check(&self, body: &[u8], truncated: bool) -> Result<(), String> {
+        let text = String::from_utf8_lossy(body);
+        let values: Vec<f64> = text
+            .lines()
+            .filter_map(parse_sample)
+            .filter(|sample| sample.name == self.name && self.labels_match(&sample.labels))
+            .map(|sample| sample.value)
+            .collect();
+        if values.is_empty() {
+            return Err(format!(
+                "metric {} not found{}",
+                self.series(),
+                if truncated {
+                    format!(" in the first {} bytes", body.len())
+                } else {
+                    String::new()
+                }
+            ));
+        }
+        let (Some(op), Some(expected)) = (self.op, self.value) else {
+            return Ok(());
+        };
+        if values.iter().any(|&v| op.holds(v, expected)) {
+            return Ok(());
+        }
+        let shown: Vec<String> = values.iter().map(f64::to_string).collect();
+        Err(format!(
+            "metric {} is {}, expected {} {}",
+            self.series(),
+            shown.join(", "),
+            op,
+            expected
+        ))
+    }
+
+    fn labels_match(&self, labels: &[(String, String)]) -> bool {
+        self.labels.iter().flatten().all(|(name, value)| {
+            labels
+                .iter()
+                .any(|(have_name, have_value)| have_name == name && have_value == value)
+        })
+    }
+
+    /// The metric as a PromQL-style selector, e.g. `up{job="api"}`.
+    fn series(&self) -> String {
+        let mut labels: Vec<String> = self
+            .labels
+            .iter()
+            .flatten()
+            .map(|(name, value)| format!("{}={:?}", name, value))
+            .collect();
+        if labels.is_empty() {
+            return self.name.clone();
+        }
+        labels.sort();
+        format!("{}{{{}}}", self.name, labels.join(","))
+    }
+}
+
+struct Sample<'a> {
+    name: &'a str,
+    labels: Vec<(String, String)>,
+    value: f64,
+}
+
+/// Parses a sample line (`name{label="value",...} value [timestamp]`); `None` for comments,
+/// blank lines and lines that do not parse.
+fn parse_sample(line: &str) -> Option<Sample<'_>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let name_end = line.find(|c: char| c == '{' || c.is_whitespace())?;
+    let name = &line[..name_end];
+    let mut rest = &line[name_end..];
+    let mut labels = Vec::new();
+    if let Some(inner) = rest.strip_prefix('{') {
+        let (parsed, after) = parse_labels(inner)?;
+        labels = parsed;
+        rest = after;
+    }
+    let value = rest.split_whitespace().next()?;
+    let value = match value {
+        "+Inf" => f64::INFINITY,
+        "-Inf" => f64::NEG_INFINITY,
+        other => other.parse().ok()?,
+    };
+    Some(Sample {
+        name,
+        labels,
+        value,
+    })
+}
+
+/// Parses `label="value",...}` and returns the labels and the text after the `}`.
+fn parse_labels(mut s: &str) -> Option<(Vec<(String, String)>, &str)> {
+    let mut labels = Vec::new();
+    loop {
+        s = s.trim_start_matches([',', ' ']);
+        if let Some(after) = s.strip_prefix('}') {
+            return Some((labels, after));
+        }
+        let (name, after) = s.split_once('=')?;
+        let mut chars = after.strip_prefix('"')?.char_indices();
+        let mut value = String::new();
+        let end = loop {
+            match chars.next()? {
+                (i, '"') => break i,
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                (_, c) => value.push(c),
+            }
+        };
+        labels.push((name.trim().to_string(), value));
+        s = &after[1 + end + 1..];
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+use std::fs;
+use std::sync::Arc;
+
+/// Differences listed in a down reason; the rest are counted.
+const MAX_REPORTED_DIFFS: usize = 3;
+/// Characters of a value quoted in a difference
+const MAX_VALUE_CHARS: usize = 60;
+
+/// Expected JSON body loaded from `golden_file`, shared by clones.
+///
+/// Values at the `golden_ignore_paths` JSON pointers (timestamps, uptimes, ...) are set to
+/// `null` on both sides before comparing.
+#[derive(Debug, Clone)]
+pub struct GoldenBody {
+    expected: Arc<Value>,
+    ignore: Arc<Vec<String>>,
+}
+
+impl GoldenBody {
+    /// Reads the golden file at `path`.
+    pub fn from_file(path: &str, ignore: &[String]) -> Result<Self> {
+        for pointer in ignore {
+            if !pointer.starts_with('/') {
+                bail!(
+                    "golden_ignore_paths entry `{}` is not a JSON pointer (e.g. /uptime_sec)",
+                    pointer
+                );
+            }
+        }
+        let raw = fs::read_to_string(path).with_context(|| format!("failed to read {}", path))?;
+        let mut expected: Value =
+            serde_json::from_str(&raw).with_context(|| format!("{} is not valid JSON", path))?;
+        blank(&mut expected, ignore);
+        Ok(Self {
+            expected: Arc::new(expected),
+            ignore: Arc::new(ignore.to_vec()),
+        })
+    }
+
+    /// Compares a response body; `Err` carries the down reason.
+    pub fn check(&self, body: &[u8]) -> Result<(), String> {
+        let mut actual: Value = serde_json::from_slice(body)
+            .map_err(|e| format!("body is not JSON, cannot compare to golden file ({})", e))?;
+        blank(&mut actual, &self.ignore);
+        let mut diffs = Vec::new();
+        diff("", &self.expected, &actual, &mut diffs);
+        if diffs.is_empty() {
+            return Ok(());
+        }
+        let mut reason = format!(
+            "body differs from golden file: {}",
+            diffs[..diffs.len().min(MAX_REPORTED_DIFFS)].join("; ")
+        );
+        if diffs.len() > MAX_REPORTED_DIFFS {
+            reason.push_str(&format!(" (and {} more)", diffs.len() - MAX_REPORTED_DIFFS));
+        }
+        Err(reason)
+    }
+}
+
+fn blank(value: &mut Value, pointers: &[String]) {
+    for pointer in pointers {
+        if let Some(v) = value.pointer_mut(pointer) {
+            *v = Value::Null;
+        }
+    }
+}
+
+/// Appends the differences between `expected` and `actual` at `path`, as JSON pointers.
+fn diff(path: &str, expected: &Value, actual: &Value, out: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(e), Value::Object(a)) => {
+            // JSON pointer escaping of a key
+            let child =
+                |key: &str| format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+            for (key, ev) in e {
+                match a.get(key) {
+                    Some(av) => diff(&child(key), ev, av, out),
+                    None => out.push(format!("{}: missing", child(key))),
+                }
+            }
+            for key in a.keys().filter(|k| !e.contains_key(*k)) {
+                out.push(format!("{}: unexpected", child(key)));
+            }
+        }
+        (Value::Array(e), Value::Array(a)) => {
+            for (i, ev) in e.iter().enumerate() {
+                let child = format!("{}/{}", path, i);
+                match a.get(i) {
+                    Some(av) => diff(&child, ev, av, out),
+                    None => out.push(format!("{}: missing", child)),
+                }
+            }
+            for i in e.len()..a.len() {
+                out.push(format!("{}/{}: unexpected", path, i));
+            }
+        }
+        _ if expected != actual => out.push(format!(
+            "{}: expected {}, got {}",
+            if path.is_empty() { "/" } else { path },
+            quote(expected),
+            quote(actual)
+        )),
+        _ => {}
+    }
+}
+
+fn quote(value: &Value) -> String {
+    let text = value.to_string();
+    if text.chars().count() > MAX_VALUE_CHARS {
+        format!(
+            "{}...",
+            text.chars().take(MAX_VALUE_CHARS).collect::<String>()
+        )
+    } else {
+        text
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{EndpointConfig, K8sDiscovery};
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const SERVICE_ACCOUNT_DIR: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+
+/// Lists target URLs from Kubernetes services via the API server.
+///
+/// Talks to the API directly with the in-cluster service account (token and CA under
+/// `/var/run/secrets/kubernetes.io/serviceaccount`) unless overridden in [`K8sDiscovery`].
+/// The token is re-read on every call so rotated tokens are picked up.
+pub struct K8sDiscoverer {
+    client: Client,
+    api_url: String,
+    namespace: String,
+    token_path: String,
+    cfg: K8sDiscovery,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServiceList {
+    #[serde(default)]
+    items: Vec<Service>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Service {
+    metadata: ObjectMeta,
+    #[serde(default)]
+    spec: ServiceSpec,
+}
+
+#[derive(Debug, Deserialize)]
+struct ObjectMeta {
+    name: String,
+    #[serde(default)]
+    namespace: Option<String>,
+    #[serde(default)]
+    annotations: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ServiceSpec {
+    #[serde(default)]
+    ports: Vec<ServicePort>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServicePort {
+    #[serde(default)]
+    name: Option<String>,
+    port: u16,
+}
+
+impl K8sDiscoverer {
+    pub fn new(cfg: &K8sDiscovery) -> Result<Self> {
+        let api_url = match &cfg.api_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => {
+                let host = std::env::var("KUBERNETES_SERVICE_HOST")
+                    .context("k8s_discovery: KUBERNETES_SERVICE_HOST not set and no api_url")?;
+                let port =
+                    std::env::var("KUBERNETES_SERVICE_PORT").unwrap_or_else(|_| "443".into());
+                format!("https://{}:{}", host, port)
+            }
+        };
+        let namespace = match &cfg.namespace {
+            Some(ns) => ns.clone(),
+            None => fs::read_to_string(Path::new(SERVICE_ACCOUNT_DIR).join("namespace"))
+                .map(|ns| ns.trim().to_string())
+                .unwrap_or_else(|_| "default".to_string()),
+        };
+        let token_path = cfg
+            .token_path
+            .clone()
+            .unwrap_or_else(|| format!("{}/token", SERVICE_ACCOUNT_DIR));
+        // Only the default in-cluster CA may be absent; a configured one must exist
+        let ca_path = match &cfg.ca_path {
+            Some(path) => Some(path.clone()),
+            None => Some(format!("{}/ca.crt", SERVICE_ACCOUNT_DIR))
+                .filter(|path| Path::new(path).exists()),
+        };
+        let mut builder = Client::builder();
+        if let Some(ca_path) = ca_path {
+            let pem = fs::read(&ca_path)
+                .with_context(|| format!("failed to read k8s CA at {}", ca_path))?;
+            let cert = reqwest::Certificate::from_pem(&pem).context("invalid PEM for k8s CA")?;
+            builder = builder.add_root_certificate(cert);
+        }
+        let client = builder.build().context("failed to build k8s API client")?;
+        Ok(Self {
+            client,
+            api_url,
+            namespace,
+            token_path,
+            cfg: cfg.clone(),
+        })
+    }
+
+    /// Lists matching services and maps each to an endpoint.
+    pub async fn discover(&self) -> Result<Vec<EndpointConfig>> {
+        let token = fs::read_to_string(&self.token_path)
+            .with_context(|| format!("failed to read k8s token at {}", self.token_path))?;
+        let mut req = self
+            .client
+            .get(format!(
+                "{}/api/v1/namespaces/{}/services",
+                self.api_url, self.namespace
+            ))
+            .bearer_auth(token.trim());
+        if let Some(selector) = &self.cfg.label_selector {
+            req = req.query(&[("labelSelector", selector)]);
+        }
+        let services: ServiceList = req
+            .send()
+            .await
+            .context("k8s service list request failed")?
+            .error_for_status()
+            .context("k8s service list request rejected")?
+            .json()
+            .await
+            .context("invalid k8s service list response")?;
+        Ok(services
+            .items
+            .iter()
+            .filter_map(|svc| self.endpoint_for(svc))
+            .collect())
+    }
+
+    fn endpoint_for(&self, svc: &Service) -> Option<EndpointConfig> {
+        if let Some(key) = &self.cfg.annotation {
+            match svc.metadata.annotations.get(key) {
+                Some(v) if v != "false" => {}
+                _ => return None,
+            }
+        }
+        let port = match &self.cfg.port_name {
+            Some(name) => svc
+                .spec
+                .ports
+                .iter()
+                .find(|p| p.name.as_deref() == Some(name.as_str()))?,
+            None => svc.spec.ports.first()?,
+        };
+        let path = self
+            .cfg
+            .path_annotation
+            .as_ref()
+            .and_then(|key| svc.metadata.annotations.get(key))
+            .unwrap_or(&self.cfg.path);
+        let namespace = svc.metadata.namespace.as_deref().unwrap_or(&self.namespace);
+        Some(EndpointConfig {
+            url: format!(
+                "{}://{}.{}.svc:{}/{}",
+                self.cfg.scheme,
+                svc.metadata.name,
+                namespace,
+                port.port,
+                path.trim_start_matches('/')
+            ),
+            ..Default::default()
+        })
+    }
+}
@@ -1,17 +1,97 @@
 use anyhow::{Context, Result};
 use futures::{StreamExt, stream};
-use metrics::{counter, histogram};
-use reqwest::{Client, StatusCode};
+use metrics::{counter, gauge, histogram};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use reqwest::{Client, Method, StatusCode};
 use schemars::JsonSchema;
 use serde::Deserialize;
 use std::fs;
-use std::path::Path;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::Semaphore;
 use tracing::{debug, error, info, warn};
 use url::Url;
 
+mod apdex;
+mod availability;
+mod body_budget;
+mod body_schema;
+mod cert;
+mod cert_trust;
+mod concurrency;
+mod csv_endpoints;
+#[cfg(feature = "dns")]
+mod dns;
+mod dns_cache;
+mod env_vars;
+mod events;
+mod expand;
+mod exposition;
+mod golden;
+mod http_responder;
+#[cfg(feature = "k8s")]
+mod k8s;
+mod markdown;
+mod notify;
+mod overrides;
+mod pagerduty;
+mod progress;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+mod raw_tcp;
+mod reason;
+mod redirect;
+mod replay;
+mod retry_after;
+mod self_health;
+mod sink;
+#[cfg(feature = "ssh")]
+mod ssh;
+mod table;
+mod template;
+mod timing;
+mod url_check;
+mod window;
+
+pub use apdex::{ApdexSample, apdex_score};
+pub use availability::AvailabilityTracker;
+pub use body_budget::BodyBudget;
+pub use body_schema::BodySchema;
+pub use cert::{CertRequirements, check_certificate};
+pub use cert_trust::{CertTrust, TrustAudit};
+pub use concurrency::{AdaptiveConcurrency, percentile};
+#[cfg(feature = "dns")]
+pub use dns::{DnsResolver, SystemResolver, check_dns};
+pub use dns_cache::DnsCache;
+pub use events::EventPublisher;
+pub use exposition::{ExpectMetric, MetricOp};
+pub use golden::GoldenBody;
+#[cfg(feature = "k8s")]
+pub use k8s::K8sDiscoverer;
+pub use markdown::render_markdown;
+pub use notify::{NotifyConfig, StateChange, StateTracker, WebhookNotifier};
+pub use overrides::{ConfigOverrides, drop_invalid_endpoints, set_config_value};
+pub use pagerduty::PagerDuty;
+use progress::SweepProgress;
+#[cfg(feature = "prometheus")]
+pub use prometheus::MetricsExporter;
+pub use reason::{DownReason, ReasonCode, error_chain, normalize_reason};
+use reason::{is_connection_failure, reason_code};
+pub use replay::{failed_endpoints, results_json, retain_endpoints};
+pub use self_health::{CheckReply, SelfHealth};
+pub use sink::{JsonlFile, MarkdownFile, ResultSink, ResultsFile, StdoutJson, emit_results};
+#[cfg(feature = "ssh")]
+pub use ssh::{SshTunnel, ssh_args};
+pub use table::{color_enabled, render_table};
+pub use template::EndpointTemplate;
+pub use timing::TimingBreakdown;
+pub use url_check::{UrlCheck, check_url};
+pub use window::RollingWindow;
+
 #[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct Config {
     /// List of HTTP/HTTPS endpoints to check
@@ -19,12 +99,43 @@ pub struct Config {
     /// Advanced endpoint configs (overrides endpoints_to_check if provided)
     #[serde(default)]
     pub endpoints: Option<Vec<EndpointConfig>>,
+    /// CSV (or `.tsv`) file of further endpoints, one per row, with a header row naming columns
+    #[serde(default)]
+    pub endpoints_csv: Option<String>,
+    /// Path (e.g. `/healthz`) appended to HTTP endpoint URLs that have none of their own
+    #[serde(default)]
+    pub health_path: Option<String>,
     /// Request timeout in milliseconds
     #[serde(default = "default_timeout_ms")]
     pub request_timeout_ms: u64,
-    /// Maximum number of concurrent checks
+    /// Scales every request timeout (global and per-endpoint), e.g. 2.0 for slow CI runners
+    #[serde(default)]
+    pub timeout_multiplier: Option<f64>,
+    /// Maximum number of concurrent checks (starting value in adaptive mode)
     #[serde(default = "default_concurrency")]
     pub concurrency: usize,
+    /// `fixed` uses `concurrency` as is; `adaptive` tunes it between watch iterations (AIMD on p95 latency)
+    #[serde(default)]
+    pub concurrency_mode: ConcurrencyMode,
+    /// Order checks are started in each sweep: `config`, `random` or `priority`
+    #[serde(default)]
+    pub order: CheckOrder,
+    /// Seed for `order: random`, for a reproducible sequence of orders
+    #[serde(default)]
+    pub order_seed: Option<u64>,
+    /// Upper bound for adaptive concurrency
+    #[serde(default = "default_concurrency_max")]
+    pub concurrency_max: usize,
+    /// Maximum number of concurrent checks against one host (URL host and port), in addition
+    /// to `concurrency`; unlimited if unset
+    #[serde(default)]
+    pub max_concurrency_per_host: Option<usize>,
+    /// Cap on response body bytes buffered at once across all in-flight checks
+    #[serde(default)]
+    pub max_inflight_body_bytes: Option<usize>,
+    /// Response body bytes buffered per check for body assertions (default 64 KiB)
+    #[serde(default)]
+    pub max_body_bytes: Option<usize>,
     /// Number of retries for each endpoint (0 = no retry)
     #[serde(default)]
     pub retries: u32,
@@ -43,9 +154,29 @@ pub struct Config {
     /// If set, periodically logs metrics (seconds). For one-shot runs, a final summary is always logged.
     #[serde(default)]
     pub metrics_log_interval_sec: Option<u64>,
+    /// Bucket boundaries (ms, ascending) for `healthcheck_latency_ms`, see [`latency_histogram_buckets`]
+    #[serde(default)]
+    pub latency_buckets: Option<Vec<f64>>,
+    /// Log sweep progress (checked, in flight, pending) at this interval (seconds; 0 disables)
+    #[serde(default = "default_progress_log_interval_sec")]
+    pub progress_log_interval_sec: u64,
     /// If set, will run repeatedly in a watch loop with this interval (seconds)
     #[serde(default)]
     pub watch_interval_sec: Option<u64>,
+    /// Watch iterations at startup left out of latency metrics (histograms, objective, Apdex,
+    /// adaptive concurrency); checks still run, log and alert as usual
+    #[serde(default)]
+    pub metrics_warmup_iterations: Option<u64>,
+    /// In watch mode, fail instead of watching on if the first sweep is unhealthy
+    #[serde(default)]
+    pub watch_require_initial_healthy: bool,
+    /// In watch mode, on SIGTERM or Ctrl-C finish with one last sweep, resolve open PagerDuty
+    /// incidents and flush events before exiting
+    #[serde(default)]
+    pub shutdown_drain: bool,
+    /// Time the shutdown drain may take, final sweep included (seconds)
+    #[serde(default = "default_shutdown_timeout_sec")]
+    pub shutdown_timeout_sec: u64,
     /// Circuit breaker: failures before opening breaker (in watch mode)
     #[serde(default = "default_cb_threshold")]
     pub cb_failures_threshold: u32,
@@ -61,9 +192,342 @@ pub struct Config {
     /// TLS: accept invalid certs (dangerous; default false)
     #[serde(default)]
     pub danger_accept_invalid_certs: bool,
-    /// TLS: optional CA bundle path (PEM) to trust
+    /// TLS: optional CA bundle path (PEM, may hold several certificates) to trust
     #[serde(default)]
     pub ca_bundle_path: Option<String>,
+    /// TLS: directory whose `.pem`/`.crt` files are all trusted as CAs
+    #[serde(default)]
+    pub ca_bundle_dir: Option<String>,
+    /// TLS: client certificate (PEM) for endpoints behind mutual TLS; needs `client_key_path`
+    #[serde(default)]
+    pub client_cert_path: Option<String>,
+    /// TLS: private key (PEM) of `client_cert_path`
+    #[serde(default)]
+    pub client_key_path: Option<String>,
+    /// TLS: minimum protocol version to negotiate (`"1.2"` or `"1.3"`)
+    #[serde(default)]
+    pub min_tls_version: Option<TlsVersion>,
+    /// TLS: record whether each https endpoint's certificate chain is trusted, even where
+    /// `danger_accept_invalid_certs` lets the check pass
+    #[serde(default)]
+    pub report_cert_validation: bool,
+    /// TLS: mark endpoints down if their certificate chain is untrusted (implies
+    /// `report_cert_validation`)
+    #[serde(default)]
+    pub fail_on_untrusted_cert: bool,
+    /// Flag endpoints whose latency is an outlier (beyond median + k·MAD) within a sweep
+    #[serde(default)]
+    pub highlight_outliers: bool,
+    /// Multiplier k applied to the median absolute deviation for outlier detection
+    #[serde(default = "default_outlier_mad_k")]
+    pub outlier_mad_k: f64,
+    /// Address (e.g. 0.0.0.0:8081) serving /livez and /readyz for the checker itself (watch mode)
+    #[serde(default)]
+    pub self_health_addr: Option<String>,
+    /// Address (e.g. 0.0.0.0:9898) serving the check metrics at /metrics for Prometheus (watch
+    /// mode, `prometheus` feature)
+    #[serde(default)]
+    pub metrics_listen_addr: Option<String>,
+    /// /readyz turns unhealthy if no sweep completed within this many watch intervals
+    #[serde(default = "default_self_health_stale_intervals")]
+    pub self_health_stale_intervals: u32,
+    /// Rolling window (seconds) over which per-endpoint Apdex is computed in watch mode
+    #[serde(default = "default_apdex_window_sec")]
+    pub apdex_window_sec: u64,
+    /// Rolling window (seconds) of the per-endpoint availability ratio in watch mode; off if unset
+    #[serde(default)]
+    pub availability_window_sec: Option<u64>,
+    /// Discover extra targets from Kubernetes services (requires the `k8s` feature)
+    #[serde(default)]
+    pub k8s_discovery: Option<K8sDiscovery>,
+    /// Reach endpoints through SSH port forwards on a jump host (requires the `ssh` feature)
+    #[serde(default)]
+    pub ssh_jump: Option<SshJump>,
+    /// Response header (e.g. X-Request-Id) whose value is recorded as the outcome's correlation id
+    #[serde(default)]
+    pub capture_response_header: Option<String>,
+    /// Fleet-wide latency objective: successful checks should complete within this many ms
+    #[serde(default)]
+    pub latency_objective_ms: Option<u64>,
+    /// Fraction of successful checks that must meet `latency_objective_ms` (e.g. 0.95)
+    #[serde(default = "default_latency_objective_ratio")]
+    pub latency_objective_ratio: f64,
+    /// Fail responses slower than this (ms) even if they pass; per-endpoint `max_latency_ms`
+    /// overrides it
+    #[serde(default)]
+    pub max_latency_ms: Option<u64>,
+    /// Report reachable endpoints answering with an unexpected status as degraded, not down
+    #[serde(default)]
+    pub report_degraded: bool,
+    /// Exit successfully when endpoints are degraded but none is down (one-shot runs)
+    #[serde(default)]
+    pub tolerate_degraded: bool,
+    /// Do not fail runs over endpoints that are down only because their check timed out
+    #[serde(default)]
+    pub tolerate_timeouts: bool,
+    /// Send `Connection: close` on every request, for legacy servers that mishandle keep-alive
+    #[serde(default)]
+    pub connection_close: bool,
+    /// Number of endpoints the config is expected to resolve to; a different count is reported
+    #[serde(default)]
+    pub expected_endpoint_count: Option<usize>,
+    /// Fail instead of warning when `expected_endpoint_count` does not match
+    #[serde(default)]
+    pub strict_endpoint_count: bool,
+    /// Named endpoint settings that `endpoints` can inherit via `template`
+    #[serde(default)]
+    pub templates: std::collections::HashMap<String, EndpointTemplate>,
+    /// On a failed DNS lookup, reuse the host's addresses if it resolved within this many seconds
+    #[serde(default)]
+    pub dns_stale_tolerance_sec: Option<u64>,
+    /// Source IP to bind outgoing requests to (multi-homed hosts)
+    #[serde(default)]
+    pub local_address: Option<IpAddr>,
+    /// Re-run an unhealthy one-shot sweep up to this many times before concluding failure
+    #[serde(default)]
+    pub sweep_retries: u32,
+    /// Delay (seconds) before each sweep retry
+    #[serde(default = "default_sweep_retry_delay_sec")]
+    pub sweep_retry_delay_sec: u64,
+    /// Handling of endpoints configured more than once (same kind, method, URL, SNI, headers and body)
+    #[serde(default)]
+    pub on_duplicate: DuplicatePolicy,
+    /// Publish every check outcome to NATS or Kafka (requires the matching feature)
+    #[serde(default)]
+    pub event_sink: Option<EventSink>,
+    /// PagerDuty Events v2 routing key; incidents follow the circuit breakers (watch mode)
+    #[serde(default)]
+    pub pagerduty_routing_key: Option<String>,
+    /// PagerDuty Events API URL (e.g. the EU service region)
+    #[serde(default = "default_pagerduty_events_url")]
+    pub pagerduty_events_url: String,
+    /// Webhook notified when an endpoint changes state (watch mode)
+    #[serde(default)]
+    pub notify: Option<NotifyConfig>,
+    /// Deliberately fail some checks, to exercise alerting end to end (needs `--allow-fault-injection`)
+    #[serde(default)]
+    pub fault_injection: Option<FaultInjection>,
+    /// Set by `--allow-fault-injection`; `fault_injection` is ignored without it
+    #[serde(skip)]
+    pub allow_fault_injection: bool,
+}
+
+impl Config {
+    /// Checks the settings that would otherwise only fail mid-run, naming the offending
+    /// field and value. Called by the `load_config` functions; [`prepare_endpoints`] validates
+    /// the rest once endpoints are resolved, and repeats the limit checks for configs built
+    /// in code.
+    pub fn validate(&self) -> Result<()> {
+        let has_endpoints = !self.endpoints_to_check.is_empty()
+            || self.endpoints.as_ref().is_some_and(|eps| !eps.is_empty())
+            || self.endpoints_csv.is_some()
+            || self.k8s_discovery.is_some();
+        if !has_endpoints {
+            anyhow::bail!(
+                "no endpoints configured: set endpoints_to_check, endpoints, endpoints_csv or \
+                 k8s_discovery"
+            );
+        }
+        self.validate_limits()?;
+        for url in &self.endpoints_to_check {
+            Url::parse(url)
+                .with_context(|| format!("endpoints_to_check: invalid url {}", redact_url(url)))?;
+        }
+        for (i, ep) in self.endpoints.iter().flatten().enumerate() {
+            // Other kinds and `expand` patterns are checked once resolved
+            if ep.kind == CheckKind::Http && !ep.expand {
+                Url::parse(&ep.url).with_context(|| {
+                    format!("endpoints[{}]: invalid url {}", i, redact_url(&ep.url))
+                })?;
+            }
+            if let Some(status) = &ep.expected_status {
+                status
+                    .validate()
+                    .with_context(|| format!("endpoints[{}] ({})", i, redact_url(&ep.url)))?;
+            }
+        }
+        for (name, template) in &self.templates {
+            if let Some(status) = &template.expected_status {
+                status
+                    .validate()
+                    .with_context(|| format!("templates.{}", name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The limits a run cannot start with, e.g. `concurrency: 0`, which would wait forever.
+    fn validate_limits(&self) -> Result<()> {
+        if self.concurrency == 0 {
+            anyhow::bail!("concurrency must be at least 1, got 0");
+        }
+        if self.base_backoff_ms > self.max_backoff_ms {
+            anyhow::bail!(
+                "base_backoff_ms ({}) exceeds max_backoff_ms ({})",
+                self.base_backoff_ms,
+                self.max_backoff_ms
+            );
+        }
+        Ok(())
+    }
+}
+
+/// HTTP version an endpoint must be served over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpVersion {
+    /// HTTP/3 over QUIC (requires the `http3` feature)
+    Http3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+pub enum TlsVersion {
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConcurrencyMode {
+    #[default]
+    Fixed,
+    Adaptive,
+}
+
+/// Order in which a sweep starts its checks (concurrency permitting).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckOrder {
+    /// As listed, with discovered endpoints last
+    #[default]
+    Config,
+    /// Shuffled anew every sweep
+    Random,
+    /// Highest `priority` first, ties in config order
+    Priority,
+}
+
+/// What to do when the same request is configured more than once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicatePolicy {
+    /// Log each duplicate and check it anyway
+    #[default]
+    Warn,
+    /// Keep the first occurrence only
+    Dedup,
+    /// Reject the config
+    Error,
+}
+
+/// Kubernetes service discovery. Matching services are merged into the endpoint set on every
+/// run (each iteration in watch mode).
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct K8sDiscovery {
+    /// Namespace to list services in (default: the pod's own namespace)
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// Server-side label selector, e.g. "app.kubernetes.io/part-of=shop"
+    #[serde(default)]
+    pub label_selector: Option<String>,
+    /// Only services carrying this annotation (with a value other than "false") are checked
+    #[serde(default)]
+    pub annotation: Option<String>,
+    /// Named service port to target (default: the first port)
+    #[serde(default)]
+    pub port_name: Option<String>,
+    /// URL scheme for discovered targets
+    #[serde(default = "default_k8s_scheme")]
+    pub scheme: String,
+    /// Health path appended to discovered targets
+    #[serde(default = "default_k8s_path")]
+    pub path: String,
+    /// Annotation whose value overrides `path` per service
+    #[serde(default)]
+    pub path_annotation: Option<String>,
+    /// API server URL (default: in-cluster from KUBERNETES_SERVICE_HOST/PORT)
+    #[serde(default)]
+    pub api_url: Option<String>,
+    /// Service account token path (default: in-cluster mount)
+    #[serde(default)]
+    pub token_path: Option<String>,
+    /// CA bundle for the API server, which must exist (default: the in-cluster mount, if present)
+    #[serde(default)]
+    pub ca_path: Option<String>,
+}
+
+/// Message bus that every check outcome is published to as JSON.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum EventSink {
+    /// NATS subject over plain TCP (requires the `nats` feature)
+    Nats {
+        /// e.g. nats://nats.internal:4222
+        url: String,
+        subject: String,
+        #[serde(default)]
+        token: Option<String>,
+    },
+    /// Kafka topic via a Kafka REST Proxy (requires the `kafka` feature)
+    Kafka {
+        /// REST Proxy base URL, e.g. http://kafka-rest:8082
+        rest_url: String,
+        topic: String,
+    },
+}
+
+impl EventSink {
+    /// Sink kind as written in the config (`nats`, `kafka`).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EventSink::Nats { .. } => "nats",
+            EventSink::Kafka { .. } => "kafka",
+        }
+    }
+}
+
+/// SSH jump host whose port forwards are held open while checks run.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct SshJump {
+    pub host: String,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Private key passed to `ssh -i`
+    #[serde(default)]
+    pub key_path: Option<String>,
+    /// Local ports to forward; endpoints target `127.0.0.1:<local_port>`
+    pub forwards: Vec<SshForward>,
+    /// ssh binary to run
+    #[serde(default = "default_ssh_command")]
+    pub command: String,
+    /// How long to wait for every forward to accept connections
+    #[serde(default = "default_ssh_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct SshForward {
+    pub local_port: u16,
+    pub remote_host: String,
+    pub remote_port: u16,
+}
+
+fn default_ssh_command() -> String {
+    "ssh".to_string()
+}
+fn default_ssh_connect_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_k8s_scheme() -> String {
+    "http".to_string()
+}
+fn default_k8s_path() -> String {
+    "/".to_string()
 }
 
 fn default_timeout_ms() -> u64 {
@@ -72,6 +536,9 @@ fn default_timeout_ms() -> u64 {
 fn default_concurrency() -> usize {
     8
 }
+fn default_concurrency_max() -> usize {
+    64
+}
 fn default_user_agent() -> String {
     "rust-healthcheck/1.0".to_string()
 }
@@ -87,6 +554,30 @@ fn default_cb_threshold() -> u32 {
 fn default_cb_cooldown_sec() -> u64 {
     60
 }
+fn default_outlier_mad_k() -> f64 {
+    3.0
+}
+fn default_self_health_stale_intervals() -> u32 {
+    3
+}
+fn default_latency_objective_ratio() -> f64 {
+    0.95
+}
+fn default_sweep_retry_delay_sec() -> u64 {
+    10
+}
+fn default_shutdown_timeout_sec() -> u64 {
+    30
+}
+fn default_pagerduty_events_url() -> String {
+    pagerduty::DEFAULT_EVENTS_URL.to_string()
+}
+fn default_progress_log_interval_sec() -> u64 {
+    5
+}
+fn default_apdex_window_sec() -> u64 {
+    300
+}
 
 #[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct ExpectedStatus {
@@ -94,289 +585,3120 @@ pub struct ExpectedStatus {
     pub min: Option<u16>,
     #[serde(default)]
     pub max: Option<u16>,
-}
-
-#[derive(Debug, Clone, Deserialize, JsonSchema)]
-pub struct EndpointConfig {
-    pub url: String,
-    #[serde(default = "default_method")]
-    pub method: String,
-    #[serde(default)]
-    pub timeout_ms: Option<u64>,
-    #[serde(default)]
-    pub retries: Option<u32>,
+    /// Named status class(es), e.g. "2xx" or ["2xx", "redirect"]
     #[serde(default)]
-    pub expected_status: Option<ExpectedStatus>,
+    pub category: Option<StatusCategories>,
+    /// Exact codes that pass, e.g. [200, 204]; replaces `min`, `max` and `category`
     #[serde(default)]
-    pub headers: Option<std::collections::HashMap<String, String>>,
+    pub allowed: Option<Vec<u16>>,
 }
 
-fn default_method() -> String {
-    "GET".to_string()
-}
+impl ExpectedStatus {
+    fn validate(&self) -> Result<()> {
+        if self.allowed.as_ref().is_some_and(Vec::is_empty) {
+            anyhow::bail!("expected_status allowed must list at least one code");
+        }
+        if let (Some(min), Some(max)) = (self.min, self.max)
+            && min > max
+        {
+            anyhow::bail!("expected_status min ({}) exceeds max ({})", min, max);
+        }
+        Ok(())
+    }
 
-#[derive(Debug, Clone)]
-pub enum HealthStatus {
-    Up,
-    Down(String),
+    /// True if `code` is in `allowed`, or, without it, satisfies every configured constraint
+    /// (category, min and max).
+    pub fn matches(&self, code: u16) -> bool {
+        if let Some(allowed) = &self.allowed {
+            return allowed.contains(&code);
+        }
+        if let Some(category) = &self.category
+            && !category.contains(code)
+        {
+            return false;
+        }
+        if let Some(min) = self.min
+            && code < min
+        {
+            return false;
+        }
+        if let Some(max) = self.max
+            && code > max
+        {
+            return false;
+        }
+        true
+    }
 }
 
-#[derive(Debug, Clone)]
-pub struct CheckOutcome {
-    pub endpoint: String,
-    pub status: HealthStatus,
-    pub latency_ms: Option<u128>,
-    pub attempts: u32,
-    pub last_http_status: Option<StatusCode>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+pub enum StatusCategory {
+    #[serde(rename = "1xx", alias = "informational")]
+    Informational,
+    #[serde(rename = "2xx", alias = "success")]
+    Success,
+    #[serde(rename = "3xx", alias = "redirect")]
+    Redirect,
+    #[serde(rename = "4xx", alias = "client_error")]
+    ClientError,
+    #[serde(rename = "5xx", alias = "server_error")]
+    ServerError,
 }
 
-#[derive(Debug, Clone, Default)]
-pub struct Summary {
-    pub total: usize,
-    pub up: usize,
-    pub down: usize,
+impl StatusCategory {
+    pub fn contains(self, code: u16) -> bool {
+        let class = match self {
+            StatusCategory::Informational => 1,
+            StatusCategory::Success => 2,
+            StatusCategory::Redirect => 3,
+            StatusCategory::ClientError => 4,
+            StatusCategory::ServerError => 5,
+        };
+        code / 100 == class
+    }
 }
 
-pub fn load_config<P: AsRef<Path>>(path: P) -> Result<Config> {
-    let path_ref = path.as_ref();
-    let bytes =
-        fs::read(path_ref).with_context(|| format!("failed to read config file {:?}", path_ref))?;
-    let ext = path_ref
-        .extension()
-        .and_then(|s| s.to_str())
-        .map(|s| s.to_ascii_lowercase())
-        .unwrap_or_else(|| "json".to_string());
-    let cfg: Config = match ext.as_str() {
-        "yaml" | "yml" => serde_yaml::from_slice(&bytes).context("failed to parse YAML config")?,
-        _ => serde_json::from_slice(&bytes).context("failed to parse JSON config")?,
-    };
-    Ok(cfg)
+/// URL a request must end up at after following redirects: exact, or `{ regex: ... }`.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum ExpectedFinalUrl {
+    Exact(String),
+    Regex { regex: String },
 }
 
-pub fn build_client(cfg: &Config) -> Result<Client> {
-    let mut builder = Client::builder()
-        .user_agent(&cfg.user_agent)
-        .timeout(Duration::from_millis(cfg.request_timeout_ms))
-        .danger_accept_invalid_certs(cfg.danger_accept_invalid_certs);
-    if let Some(path) = &cfg.ca_bundle_path {
-        let pem =
-            fs::read(path).with_context(|| format!("failed to read ca bundle at {}", path))?;
-        let cert = reqwest::Certificate::from_pem(&pem).context("invalid PEM for CA bundle")?;
-        builder = builder.add_root_certificate(cert);
+impl std::fmt::Display for ExpectedFinalUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exact(url) => write!(f, "{}", url),
+            Self::Regex { regex } => write!(f, "/{}/", regex),
+        }
     }
-    let client = builder.build().context("failed to build reqwest client")?;
-    Ok(client)
 }
 
-fn redact_url(input: &str) -> String {
-    if let Ok(u) = Url::parse(input) {
-        let mut redacted = u.clone();
-        redacted.set_query(None);
-        return redacted.to_string();
-    }
-    input.to_string()
+/// Credentials sent with an HTTP check: `{ bearer: TOKEN }` or
+/// `{ basic: { username, password } }`. Secrets are masked in debug output.
+#[derive(Clone, PartialEq, Eq, Hash, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EndpointAuth {
+    Bearer(String),
+    Basic { username: String, password: String },
 }
 
-fn status_matches_expected(status: StatusCode, expected: &Option<ExpectedStatus>) -> bool {
-    if let Some(e) = expected {
-        let code = status.as_u16();
-        if let Some(min) = e.min
-            && code < min
-        {
-            return false;
-        }
-        if let Some(max) = e.max
-            && code > max
-        {
-            return false;
-        }
-        true
-    } else {
-        status.is_success()
+impl EndpointAuth {
+    /// The `Authorization` value, marked sensitive so it stays out of debug output.
+    fn header_value(&self) -> Result<reqwest::header::HeaderValue> {
+        use base64::Engine;
+        let value = match self {
+            Self::Bearer(token) => format!("Bearer {}", token),
+            Self::Basic { username, password } => format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD
+                    .encode(format!("{}:{}", username, password))
+            ),
+        };
+        let mut value = reqwest::header::HeaderValue::from_str(&value)
+            .context("auth credentials are not a valid header value")?;
+        value.set_sensitive(true);
+        Ok(value)
     }
 }
 
-pub async fn check_endpoint_once(
-    client: &Client,
-    ep: &EndpointConfig,
-    default_timeout_ms: u64,
-) -> CheckOutcome {
-    let start = Instant::now();
-    let mut req = match ep.method.as_str() {
-        "HEAD" => client.head(&ep.url),
-        _ => client.get(&ep.url),
-    };
-    if let Some(hs) = &ep.headers {
-        for (k, v) in hs {
-            req = req.header(k, v);
-        }
-    }
-    req = req.timeout(Duration::from_millis(
-        ep.timeout_ms.unwrap_or(default_timeout_ms),
-    ));
-    match req.send().await {
-        Ok(resp) => {
-            let status = resp.status();
-            if status_matches_expected(status, &ep.expected_status) {
-                let latency = start.elapsed().as_millis();
-                histogram!("healthcheck_latency_ms").record(latency as f64);
-                counter!("healthcheck_up_total").increment(1);
-                CheckOutcome {
-                    endpoint: redact_url(&ep.url),
-                    status: HealthStatus::Up,
-                    latency_ms: Some(latency),
-                    attempts: 1,
-                    last_http_status: Some(status),
-                }
-            } else {
-                counter!("healthcheck_down_total").increment(1);
-                CheckOutcome {
-                    endpoint: redact_url(&ep.url),
-                    status: HealthStatus::Down(format!("HTTP {}", status)),
-                    latency_ms: None,
-                    attempts: 1,
-                    last_http_status: Some(status),
-                }
-            }
-        }
-        Err(e) => {
-            counter!("healthcheck_down_total").increment(1);
-            CheckOutcome {
-                endpoint: redact_url(&ep.url),
-                status: HealthStatus::Down(e.to_string()),
-                latency_ms: None,
-                attempts: 1,
-                last_http_status: None,
-            }
+impl std::fmt::Debug for EndpointAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bearer(_) => f.write_str("Bearer(***)"),
+            Self::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &"***")
+                .finish(),
         }
     }
 }
 
-pub async fn check_with_retries(
-    client: &Client,
-    ep: &EndpointConfig,
-    retries: u32,
-    default_timeout_ms: u64,
-    base_backoff_ms: u64,
-    max_backoff_ms: u64,
-) -> CheckOutcome {
-    let mut attempt: u32 = 0;
-    let mut last_outcome = check_endpoint_once(client, ep, default_timeout_ms).await;
-    last_outcome.attempts = 1;
-    while attempt < retries {
-        match last_outcome.status {
-            HealthStatus::Up => break,
-            HealthStatus::Down(_) => {
-                attempt += 1;
-                warn!(
-                    endpoint = ep.url.as_str(),
-                    attempt = attempt,
-                    "retrying failed endpoint"
-                );
-                // backoff with jitter
-                let factor = 2u64.saturating_pow(attempt.min(20));
-                let base = base_backoff_ms.saturating_mul(factor);
-                let delay = base.min(max_backoff_ms);
-                let jitter = rand::random::<u64>() % (delay / 2 + 1);
-                tokio::time::sleep(Duration::from_millis(delay + jitter)).await;
-                let outcome = check_endpoint_once(client, ep, default_timeout_ms).await;
-                last_outcome = outcome;
-                last_outcome.attempts = attempt + 1;
-                if matches!(last_outcome.status, HealthStatus::Up) {
-                    break;
-                }
-            }
+/// One category or a list of categories; a code matches if it is in any of them.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum StatusCategories {
+    One(StatusCategory),
+    Many(Vec<StatusCategory>),
+}
+
+impl StatusCategories {
+    pub fn contains(&self, code: u16) -> bool {
+        match self {
+            StatusCategories::One(c) => c.contains(code),
+            StatusCategories::Many(cs) => cs.iter().any(|c| c.contains(code)),
         }
     }
-    last_outcome
 }
 
-pub async fn run_healthchecks(cfg: &Config) -> Result<Summary> {
-    let endpoints: Vec<EndpointConfig> = if let Some(adv) = &cfg.endpoints {
-        adv.clone()
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct EndpointConfig {
+    /// URL to check; for `kind: dns`, the hostname to resolve (a URL's host is also accepted)
+    pub url: String,
+    /// What to check: `http` (default), `dns`, `tcp` or `raw_tcp`
+    #[serde(default)]
+    pub kind: CheckKind,
+    /// Record expectation for `kind: dns` checks
+    #[serde(default)]
+    pub dns: Option<DnsExpectation>,
+    /// Request and expected response for `kind: raw_tcp` checks
+    #[serde(default)]
+    pub raw_tcp_expect: Option<RawTcpExpect>,
+    /// `raw_tcp_expect.regex`, compiled by [`prepare_endpoints`]
+    #[serde(skip)]
+    pub raw_tcp_regex: Option<regex::Regex>,
+    /// Name of a template in `templates` to inherit unset fields from
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Expands `{1..20}` ranges and `{a,b,c}` lists in `url` into one endpoint per URL
+    #[serde(default)]
+    pub expand: bool,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub retries: Option<u32>,
+    /// Overrides the global `base_backoff_ms`
+    #[serde(default)]
+    pub base_backoff_ms: Option<u64>,
+    /// Overrides the global `max_backoff_ms`
+    #[serde(default)]
+    pub max_backoff_ms: Option<u64>,
+    /// Immediate re-sends on connect errors and resets, before the backoff retry loop
+    #[serde(default)]
+    pub connection_retries: Option<u32>,
+    /// Each retry's timeout is the previous attempt's times this factor (at least 1)
+    #[serde(default)]
+    pub retry_timeout_multiplier: Option<f64>,
+    /// Cap on escalated retry timeouts; defaults to 60000
+    #[serde(default)]
+    pub retry_timeout_max_ms: Option<u64>,
+    #[serde(default)]
+    pub expected_status: Option<ExpectedStatus>,
+    #[serde(default)]
+    pub headers: Option<std::collections::HashMap<String, String>>,
+    /// Credentials for the request; replaces any Authorization in `headers` (HTTP only)
+    #[serde(default)]
+    pub auth: Option<EndpointAuth>,
+    /// Response headers that must not be present (case-insensitive), e.g. `Server`
+    #[serde(default)]
+    pub forbidden_headers: Option<Vec<String>>,
+    /// Diagnostic probe run only when the primary check fails; its result is appended to the reason
+    #[serde(default)]
+    pub on_failure_probe: Option<FailureProbe>,
+    /// Inline request body
+    #[serde(default)]
+    pub body: Option<String>,
+    /// Path to a file whose contents are sent as the request body (exclusive with `body`)
+    #[serde(default)]
+    pub body_file: Option<String>,
+    /// Content-Type of the body; inferred from the `body_file` extension if unset
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Apdex target T in ms (satisfied <= T, tolerating <= 4T); enables Apdex in watch mode
+    #[serde(default)]
+    pub apdex_target_ms: Option<u64>,
+    /// Weight in the composite health score (default 1.0)
+    #[serde(default)]
+    pub weight: Option<f64>,
+    /// Replicas tried in turn on each retry after the primary `url` fails
+    #[serde(default)]
+    pub failover_urls: Option<Vec<String>>,
+    /// Fail if the response has a non-empty body (e.g. 204 liveness endpoints)
+    #[serde(default)]
+    pub expect_empty_body: bool,
+    /// Flag passing responses whose body looks like a proxy/server error page
+    #[serde(default)]
+    pub detect_soft_errors: bool,
+    /// Markers for `detect_soft_errors` (case-insensitive); replaces the built-in list
+    #[serde(default)]
+    pub soft_error_markers: Option<Vec<String>>,
+    /// Fail if the first body byte arrives later than this (ms), regardless of total latency
+    #[serde(default)]
+    pub max_ttfb_ms: Option<u64>,
+    /// Fail if the response headers arrive later than this (ms), even with a passing status
+    #[serde(default)]
+    pub max_latency_ms: Option<u64>,
+    /// Fail if the response body size (bytes) is outside this range
+    #[serde(default)]
+    pub expected_body_size: Option<BodySizeRange>,
+    /// Path to a JSON Schema the (JSON) response body must satisfy
+    #[serde(default)]
+    pub expected_body_schema: Option<String>,
+    /// `expected_body_schema`, compiled by [`prepare_endpoints`]
+    #[serde(skip)]
+    pub body_schema: Option<BodySchema>,
+    /// Path to a JSON file the (JSON) response body must equal
+    #[serde(default)]
+    pub golden_file: Option<String>,
+    /// JSON pointers (e.g. `/uptime_sec`) left out of the `golden_file` comparison
+    #[serde(default)]
+    pub golden_ignore_paths: Option<Vec<String>>,
+    /// Metric the (Prometheus text format) response body must expose
+    #[serde(default)]
+    pub expect_metric: Option<ExpectMetric>,
+    /// Charset the body must decode in without invalid byte sequences, e.g. `utf-8`
+    #[serde(default)]
+    pub expect_charset: Option<String>,
+    /// Substring the body must contain
+    #[serde(default)]
+    pub body_contains: Option<String>,
+    /// Regex the body must match
+    #[serde(default)]
+    pub body_matches: Option<String>,
+    /// `body_matches`, compiled by [`prepare_endpoints`]
+    #[serde(skip)]
+    pub body_regex: Option<regex::Regex>,
+    /// Overrides the global `max_body_bytes`
+    #[serde(default)]
+    pub max_body_bytes: Option<usize>,
+    /// `golden_file`, loaded by [`prepare_endpoints`]
+    #[serde(skip)]
+    pub golden: Option<GoldenBody>,
+    /// Shared `max_inflight_body_bytes` budget, attached by the runners
+    #[serde(skip)]
+    pub body_budget: Option<BodyBudget>,
+    /// Certificate trust audit, attached by [`prepare_endpoints`] with `report_cert_validation`
+    #[serde(skip)]
+    pub trust_audit: Option<TrustAudit>,
+    /// Global `fault_injection`, attached by [`prepare_endpoints`] to matching endpoints
+    #[serde(skip)]
+    pub fault_injection: Option<FaultInjection>,
+    /// Overrides the global `capture_response_header`
+    #[serde(default)]
+    pub capture_response_header: Option<String>,
+    /// Overrides the global `report_degraded`
+    #[serde(default)]
+    pub report_degraded: Option<bool>,
+    /// Send `Connection: close` so the connection is not reused; overrides the global setting
+    #[serde(default)]
+    pub connection_close: Option<bool>,
+    /// Send the request a second time and report whether the connection was reused
+    #[serde(default)]
+    pub verify_keepalive: bool,
+    /// Fail unless the request ends up at this URL after following redirects
+    #[serde(default)]
+    pub expected_final_url: Option<ExpectedFinalUrl>,
+    /// `expected_final_url` regex, compiled by [`prepare_endpoints`]
+    #[serde(skip)]
+    pub final_url_regex: Option<regex::Regex>,
+    /// Fail if following redirects went from https to plain http at any hop
+    #[serde(default)]
+    pub detect_scheme_downgrade: bool,
+    /// Bypass intermediary caches: add a `_hc=<timestamp>` query parameter and
+    /// `Cache-Control`/`Pragma: no-cache` headers
+    #[serde(default)]
+    pub anti_cache: bool,
+    /// With `order: priority`, higher-priority endpoints are checked first (default 0)
+    #[serde(default)]
+    pub priority: i32,
+    /// Policy the served certificate must meet (HTTPS only)
+    #[serde(default)]
+    pub cert_requirements: Option<CertRequirements>,
+    /// Overrides the global `user_agent`; takes precedence over a User-Agent in `headers`
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// Sent as the Accept header (taking precedence over `headers`); the response's
+    /// Content-Type must match one of its media ranges, and a 406 fails with a clear reason
+    #[serde(default)]
+    pub accept: Option<String>,
+    /// Overrides the global `local_address`
+    #[serde(default)]
+    pub local_address: Option<IpAddr>,
+    /// TLS server name (and Host) to use instead of the URL's host, which is still the host
+    /// connected to; the certificate is verified against this name
+    #[serde(default)]
+    pub sni: Option<String>,
+    /// Require the response to use this HTTP version; `http3` connects over QUIC directly and
+    /// marks the endpoint down if the server answers any other way
+    #[serde(default)]
+    pub http_version: Option<HttpVersion>,
+}
+
+impl Default for EndpointConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            kind: CheckKind::Http,
+            dns: None,
+            raw_tcp_expect: None,
+            raw_tcp_regex: None,
+            template: None,
+            expand: false,
+            method: default_method(),
+            timeout_ms: None,
+            retries: None,
+            base_backoff_ms: None,
+            max_backoff_ms: None,
+            connection_retries: None,
+            retry_timeout_multiplier: None,
+            retry_timeout_max_ms: None,
+            expected_status: None,
+            headers: None,
+            auth: None,
+            forbidden_headers: None,
+            on_failure_probe: None,
+            body: None,
+            body_file: None,
+            content_type: None,
+            apdex_target_ms: None,
+            weight: None,
+            failover_urls: None,
+            expect_empty_body: false,
+            detect_soft_errors: false,
+            soft_error_markers: None,
+            max_ttfb_ms: None,
+            max_latency_ms: None,
+            expected_body_size: None,
+            expected_body_schema: None,
+            body_schema: None,
+            golden_file: None,
+            expect_metric: None,
+            expect_charset: None,
+            body_contains: None,
+            body_matches: None,
+            body_regex: None,
+            max_body_bytes: None,
+            golden_ignore_paths: None,
+            golden: None,
+            body_budget: None,
+            trust_audit: None,
+            fault_injection: None,
+            capture_response_header: None,
+            report_degraded: None,
+            connection_close: None,
+            verify_keepalive: false,
+            expected_final_url: None,
+            final_url_regex: None,
+            detect_scheme_downgrade: false,
+            anti_cache: false,
+            priority: 0,
+            cert_requirements: None,
+            user_agent: None,
+            accept: None,
+            local_address: None,
+            sni: None,
+            http_version: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckKind {
+    #[default]
+    Http,
+    /// Resolve the host and compare records against `dns` (requires the `dns` feature)
+    Dns,
+    /// Plain TCP exchange matched against `raw_tcp_expect`, for non-HTTP or non-conformant servers
+    #[serde(rename = "raw_tcp")]
+    RawTcp,
+    /// TCP connect only, for services such as databases where an accepted connection is enough
+    Tcp,
+}
+
+/// Inclusive bounds on the response body size in bytes.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+pub struct BodySizeRange {
+    #[serde(default)]
+    pub min: Option<usize>,
+    #[serde(default)]
+    pub max: Option<usize>,
+}
+
+impl BodySizeRange {
+    pub fn contains(&self, size: usize) -> bool {
+        self.min.is_none_or(|min| size >= min) && self.max.is_none_or(|max| size <= max)
+    }
+}
+
+impl std::fmt::Display for BodySizeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(min) = self.min {
+            write!(f, "{}", min)?;
+        }
+        f.write_str("..")?;
+        if let Some(max) = self.max {
+            write!(f, "{}", max)?;
+        }
+        Ok(())
+    }
+}
+
+/// Expected DNS state for a `kind: dns` check.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct DnsExpectation {
+    pub record_type: DnsRecordType,
+    /// Exact set of addresses (A/AAAA) or target names (CNAME) the host must resolve to
+    pub expected: Vec<String>,
+}
+
+/// Exchange for a `kind: raw_tcp` check.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct RawTcpExpect {
+    /// Sent as is after connecting (e.g. `"GET /\r\n"`); nothing is sent when unset
+    #[serde(default)]
+    pub send: Option<String>,
+    /// Substring the response must contain
+    #[serde(default)]
+    pub contains: Option<String>,
+    /// Regular expression the response (decoded lossily as UTF-8) must match
+    #[serde(default)]
+    pub regex: Option<String>,
+    /// Bytes read before giving up on a match (default 4096)
+    #[serde(default)]
+    pub max_bytes: Option<usize>,
+}
+
+/// Checks failed on purpose, for testing breakers, notifications and flap handling in staging.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct FaultInjection {
+    /// URLs (as configured) of the endpoints to fail; every endpoint if empty
+    #[serde(default)]
+    pub endpoints: Vec<String>,
+    /// Probability (0.0 to 1.0) that a check of a matching endpoint is failed
+    pub rate: f64,
+    /// How an injected failure looks: `down` (default) or `timeout`
+    #[serde(default)]
+    pub fault: InjectedFault,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum InjectedFault {
+    /// Fail at once
+    #[default]
+    Down,
+    /// Wait for the request timeout, then fail as a timeout
+    Timeout,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DnsRecordType {
+    A,
+    Aaaa,
+    Cname,
+}
+
+/// Default `max_body_bytes`: response bytes buffered for body assertions
+const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Built-in `detect_soft_errors` markers: default error pages of common proxies and servers.
+pub const DEFAULT_SOFT_ERROR_MARKERS: &[&str] = &[
+    "502 Bad Gateway",
+    "503 Service Unavailable",
+    "504 Gateway Time-out",
+    "504 Gateway Timeout",
+    "500 Internal Server Error",
+    "Service Temporarily Unavailable",
+    "nginx error",
+    "The page you are looking for is temporarily unavailable",
+    "Application Error",
+];
+
+/// The request method for an endpoint's `method`, case-insensitively; `None` if unsupported.
+pub(crate) fn http_method(name: &str) -> Option<Method> {
+    Some(match name.to_ascii_uppercase().as_str() {
+        "GET" => Method::GET,
+        "HEAD" => Method::HEAD,
+        "POST" => Method::POST,
+        "PUT" => Method::PUT,
+        "PATCH" => Method::PATCH,
+        "DELETE" => Method::DELETE,
+        "OPTIONS" => Method::OPTIONS,
+        _ => return None,
+    })
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+/// Secondary check used to classify a failure of the primary HTTP check.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum FailureProbe {
+    /// TCP connect to the endpoint host; port defaults to the URL's port
+    Tcp {
+        #[serde(default)]
+        port: Option<u16>,
+    },
+    /// DNS lookup of the endpoint host
+    Dns,
+}
+
+/// Result of a check. [`CheckOutcome::reason_code`] classifies degraded and down outcomes
+/// alike, with codes that do not change with message wording.
+#[derive(Debug, Clone)]
+pub enum HealthStatus {
+    Up,
+    /// Reachable, but answered with an unexpected status (only with `report_degraded`)
+    Degraded(String),
+    Down(DownReason),
+}
+
+impl HealthStatus {
+    /// The degraded or down reason as reported; `None` when up.
+    pub fn reason(&self) -> Option<String> {
+        match self {
+            HealthStatus::Up => None,
+            HealthStatus::Degraded(reason) => Some(reason.clone()),
+            HealthStatus::Down(reason) => Some(reason.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckOutcome {
+    pub endpoint: String,
+    pub status: HealthStatus,
+    pub latency_ms: Option<u128>,
+    pub attempts: u32,
+    pub last_http_status: Option<StatusCode>,
+    /// Latency is a statistical outlier relative to the rest of the sweep
+    pub outlier: bool,
+    /// Redacted URL that produced the final result, when failover targets are configured
+    pub served_by: Option<String>,
+    /// Time from request start to the first body byte (time to headers for empty bodies)
+    pub ttfb_ms: Option<u128>,
+    /// Time from request start until the body was fully read
+    pub total_ms: Option<u128>,
+    /// Value of the captured response header (see `capture_response_header`)
+    pub correlation_id: Option<String>,
+    /// DNS and connect phases, when the check opened a new connection
+    pub timing: TimingBreakdown,
+    /// Local address the request was bound to, if `local_address` is configured
+    pub source_address: Option<IpAddr>,
+    /// Peer address that served the response, when the HTTP backend exposes it
+    pub remote_addr: Option<SocketAddr>,
+    /// Redacted URLs the request was redirected through, starting with the requested one
+    pub redirect_chain: Vec<String>,
+    /// TLS server name sent instead of the URL's host (see `sni`)
+    pub sni: Option<String>,
+    /// The endpoint's weight in the composite score
+    pub weight: f64,
+    /// Full error detail for request failures, whose `status` carries a normalized reason
+    pub raw_reason: Option<String>,
+    /// Stable class of the failure, set whenever `status` is degraded or down
+    pub reason_code: Option<ReasonCode>,
+    /// Certificate chain audit result (see `report_cert_validation`)
+    pub cert_trust: Option<CertTrust>,
+    /// Whether a follow-up request had to open a new connection (see `verify_keepalive`)
+    pub keepalive_broken: Option<bool>,
+    /// Wait requested by the response's `Retry-After` header, honored between retries
+    pub retry_after_ms: Option<u64>,
+}
+
+impl CheckOutcome {
+    pub fn new(endpoint: String, status: HealthStatus) -> Self {
+        Self {
+            endpoint,
+            status,
+            latency_ms: None,
+            attempts: 1,
+            last_http_status: None,
+            outlier: false,
+            served_by: None,
+            ttfb_ms: None,
+            total_ms: None,
+            correlation_id: None,
+            timing: TimingBreakdown::default(),
+            source_address: None,
+            remote_addr: None,
+            redirect_chain: Vec::new(),
+            sni: None,
+            weight: 1.0,
+            raw_reason: None,
+            reason_code: None,
+            cert_trust: None,
+            keepalive_broken: None,
+            retry_after_ms: None,
+        }
+    }
+
+    /// JSON form of the outcome; optional fields are omitted when unset.
+    pub fn to_json(&self) -> serde_json::Value {
+        let status = match &self.status {
+            HealthStatus::Up => "up",
+            HealthStatus::Degraded(_) => "degraded",
+            HealthStatus::Down(_) => "down",
+        };
+        let reason = self.status.reason();
+        let mut json = serde_json::json!({
+            "endpoint": self.endpoint,
+            "status": status,
+            "attempts": self.attempts,
+        });
+        let optional = [
+            ("reason", reason.map(|r| r.as_str().into())),
+            ("reason_code", self.reason_code.map(|c| c.as_str().into())),
+            ("raw_reason", self.raw_reason.as_deref().map(Into::into)),
+            (
+                "cert_trusted",
+                self.cert_trust
+                    .as_ref()
+                    .map(|t| (*t == CertTrust::Trusted).into()),
+            ),
+            (
+                "cert_trust_error",
+                match &self.cert_trust {
+                    Some(CertTrust::Untrusted(reason)) => Some(reason.as_str().into()),
+                    _ => None,
+                },
+            ),
+            ("keepalive_broken", self.keepalive_broken.map(Into::into)),
+            ("retry_after_ms", self.retry_after_ms.map(Into::into)),
+            ("latency_ms", self.latency_ms.map(|v| (v as u64).into())),
+            ("ttfb_ms", self.ttfb_ms.map(|v| (v as u64).into())),
+            ("total_ms", self.total_ms.map(|v| (v as u64).into())),
+            ("dns_ms", self.timing.dns_ms.map(|v| (v as u64).into())),
+            (
+                "connect_ms",
+                self.timing.connect_ms.map(|v| (v as u64).into()),
+            ),
+            (
+                "http_status",
+                self.last_http_status.map(|s| s.as_u16().into()),
+            ),
+            ("served_by", self.served_by.as_deref().map(Into::into)),
+            (
+                "correlation_id",
+                self.correlation_id.as_deref().map(Into::into),
+            ),
+            (
+                "source_address",
+                self.source_address.map(|a| a.to_string().into()),
+            ),
+            (
+                "remote_addr",
+                self.remote_addr.map(|a| a.to_string().into()),
+            ),
+            (
+                "redirect_chain",
+                (!self.redirect_chain.is_empty()).then(|| self.redirect_chain.clone().into()),
+            ),
+            ("sni", self.sni.as_deref().map(Into::into)),
+            ("outlier", self.outlier.then_some(true.into())),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                json[key] = value;
+            }
+        }
+        json
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    pub total: usize,
+    pub up: usize,
+    pub degraded: usize,
+    pub down: usize,
+    /// Down endpoints whose check timed out (included in `down`)
+    pub timed_out: usize,
+    /// Whether the latency objective was met; `None` without an objective or successful checks
+    pub latency_objective_met: Option<bool>,
+    /// Set when the resolved endpoint count differs from `expected_endpoint_count`
+    pub endpoint_count_mismatch: Option<EndpointCountMismatch>,
+    /// Weighted health score (0–100), see [`composite_score`]
+    pub composite_score: Option<f64>,
+    /// Share of up checks per endpoint over `availability_window_sec` (watch mode)
+    pub availability: std::collections::BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndpointCountMismatch {
+    pub expected: usize,
+    pub actual: usize,
+}
+
+impl Summary {
+    /// Whether the sweep passes: nothing down, degraded only if tolerated, and the latency
+    /// objective (if any) met.
+    pub fn is_healthy(&self, cfg: &Config) -> bool {
+        !self.has_failures(cfg) && self.latency_objective_met != Some(false)
+    }
+
+    /// Whether an endpoint is down or degraded, short of what `tolerate_degraded` and
+    /// `tolerate_timeouts` allow.
+    pub fn has_failures(&self, cfg: &Config) -> bool {
+        let tolerated_down = if cfg.tolerate_timeouts {
+            self.timed_out
+        } else {
+            0
+        };
+        self.down > tolerated_down || (self.degraded > 0 && !cfg.tolerate_degraded)
+    }
+
+    /// Summary as printed with `summary_json`.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut json = serde_json::json!({
+            "total": self.total,
+            "up": self.up,
+            "degraded": self.degraded,
+            "down": self.down,
+            "timed_out": self.timed_out
+        });
+        if let Some(met) = self.latency_objective_met {
+            json["latency_objective_met"] = met.into();
+        }
+        if let Some(m) = self.endpoint_count_mismatch {
+            json["endpoint_count"] =
+                serde_json::json!({ "expected": m.expected, "actual": m.actual });
+        }
+        if let Some(score) = self.composite_score {
+            json["composite_score"] = score.into();
+        }
+        if !self.availability.is_empty() {
+            json["availability"] = serde_json::json!(self.availability);
+        }
+        json
+    }
+}
+
+/// Loads and validates a config file (see [`Config::validate`]).
+pub fn load_config<P: AsRef<Path>>(path: P) -> Result<Config> {
+    let cfg: Config = parse_config_file(path.as_ref())?;
+    cfg.validate()?;
+    Ok(cfg)
+}
+
+/// Loads a config and applies command-line overrides before anything else sees it.
+///
+/// `--set` assignments patch the raw document, so the result is validated like a config file.
+/// In best-effort mode invalid `endpoints` entries are then dropped with a warning.
+pub fn load_config_with_overrides<P: AsRef<Path>>(
+    path: P,
+    overrides: &ConfigOverrides,
+) -> Result<Config> {
+    let mut cfg: Config = if overrides.set.is_empty() && !overrides.best_effort {
+        parse_config_file(path.as_ref())?
+    } else {
+        let mut doc: serde_json::Value = parse_config_file(path.as_ref())?;
+        for assignment in &overrides.set {
+            set_config_value(&mut doc, assignment)?;
+        }
+        if overrides.best_effort {
+            drop_invalid_endpoints(&mut doc);
+        }
+        let context = if overrides.set.is_empty() {
+            "invalid config"
+        } else {
+            "invalid config after --set overrides"
+        };
+        serde_json::from_value(doc).context(context)?
+    };
+    overrides.apply(&mut cfg);
+    cfg.validate()?;
+    Ok(cfg)
+}
+
+/// Like [`load_config`], but reads the file with `tokio::fs`, so a caller on a
+/// current-thread runtime is not blocked by slow storage.
+pub async fn load_config_async<P: AsRef<Path>>(path: P) -> Result<Config> {
+    let path_ref = path.as_ref();
+    let bytes = tokio::fs::read(path_ref)
+        .await
+        .with_context(|| format!("failed to read config file {:?}", path_ref))?;
+    let cfg: Config = parse_config_bytes(path_ref, &bytes)?;
+    cfg.validate()?;
+    Ok(cfg)
+}
+
+fn parse_config_file<T: serde::de::DeserializeOwned>(path_ref: &Path) -> Result<T> {
+    let bytes =
+        fs::read(path_ref).with_context(|| format!("failed to read config file {:?}", path_ref))?;
+    parse_config_bytes(path_ref, &bytes)
+}
+
+/// Parses a config file's contents as YAML or JSON, by the extension of `path_ref`.
+///
+/// `${VAR}` placeholders in string values are resolved from the environment after parsing.
+fn parse_config_bytes<T: serde::de::DeserializeOwned>(path_ref: &Path, bytes: &[u8]) -> Result<T> {
+    let ext = path_ref
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_ascii_lowercase())
+        .unwrap_or_else(|| "json".to_string());
+    if bytes.windows(2).any(|w| w == b"${") {
+        // Interpolation needs the generic document; files without placeholders are parsed
+        // directly, which keeps line numbers in type errors
+        let mut doc: serde_json::Value = parse_config_document(&ext, bytes)?;
+        env_vars::interpolate_env(&mut doc)?;
+        return serde_json::from_value(doc).context("invalid config");
+    }
+    parse_config_document(&ext, bytes)
+}
+
+fn parse_config_document<T: serde::de::DeserializeOwned>(ext: &str, bytes: &[u8]) -> Result<T> {
+    let cfg = match ext {
+        "yaml" | "yml" => serde_yaml::from_slice(bytes).context("failed to parse YAML config")?,
+        _ => serde_json::from_slice(bytes).context("failed to parse JSON config")?,
+    };
+    Ok(cfg)
+}
+
+/// Resolves the configured endpoints into the list that is actually checked.
+///
+/// Advanced `endpoints` take precedence over `endpoints_to_check`. Templates are applied and
+/// request bodies referenced via `body_file` are read (and `expected_body_schema` compiled) here,
+/// so an unknown template or a missing file fails the run before any check starts.
+pub fn prepare_endpoints(cfg: &Config) -> Result<Vec<EndpointConfig>> {
+    // Configs built in code skip the loaders' validation
+    cfg.validate_limits()?;
+    #[cfg(not(feature = "k8s"))]
+    if cfg.k8s_discovery.is_some() {
+        anyhow::bail!("k8s_discovery requires building with the `k8s` feature");
+    }
+    #[cfg(not(feature = "ssh"))]
+    if cfg.ssh_jump.is_some() {
+        anyhow::bail!("ssh_jump requires building with the `ssh` feature");
+    }
+    #[cfg(not(feature = "nats"))]
+    if let Some(EventSink::Nats { .. }) = &cfg.event_sink {
+        anyhow::bail!("event_sink `nats` requires building with the `nats` feature");
+    }
+    #[cfg(not(feature = "kafka"))]
+    if let Some(EventSink::Kafka { .. }) = &cfg.event_sink {
+        anyhow::bail!("event_sink `kafka` requires building with the `kafka` feature");
+    }
+    let mut endpoints: Vec<EndpointConfig> = if let Some(adv) = &cfg.endpoints {
+        adv.clone()
     } else {
         cfg.endpoints_to_check
             .iter()
             .map(|u| EndpointConfig {
                 url: u.clone(),
-                method: default_method(),
-                timeout_ms: None,
-                retries: None,
-                expected_status: None,
-                headers: None,
+                ..Default::default()
             })
             .collect()
     };
+    if let Some(path) = &cfg.endpoints_csv {
+        endpoints.extend(csv_endpoints::load_endpoints_csv(path)?);
+    }
+    let mut endpoints = expand::expand_endpoints(endpoints)?;
+    if let Some(path) = &cfg.health_path {
+        apply_health_path(&mut endpoints, path)?;
+    }
+    template::apply_templates(&mut endpoints, &cfg.templates)?;
+    let mut endpoints = handle_duplicates(endpoints, cfg.on_duplicate)?;
+    if let Some(multiplier) = cfg.timeout_multiplier {
+        if !(multiplier.is_finite() && multiplier > 0.0) {
+            anyhow::bail!(
+                "timeout_multiplier must be a positive number, got {}",
+                multiplier
+            );
+        }
+        info!(
+            multiplier,
+            request_timeout_ms = scaled_timeout_ms(cfg, cfg.request_timeout_ms),
+            "scaling request timeouts"
+        );
+    }
+    if cfg.metrics_listen_addr.is_some() && cfg!(not(feature = "prometheus")) {
+        anyhow::bail!("`metrics_listen_addr` requires building with the `prometheus` feature");
+    }
+    if let Some(notify) = &cfg.notify {
+        Url::parse(&notify.webhook_url).with_context(|| {
+            format!(
+                "invalid notify.webhook_url {}",
+                redact_url(&notify.webhook_url)
+            )
+        })?;
+    }
+    if cfg.availability_window_sec == Some(0) {
+        anyhow::bail!("availability_window_sec must be positive");
+    }
+    if let Some(buckets) = &cfg.latency_buckets {
+        if buckets.is_empty() || buckets.iter().any(|b| !b.is_finite()) {
+            anyhow::bail!("latency_buckets must be a non-empty list of numbers");
+        }
+        if let Some(pair) = buckets.windows(2).find(|pair| pair[0] >= pair[1]) {
+            anyhow::bail!(
+                "latency_buckets must be strictly ascending, got {} before {}",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+    if cfg.max_concurrency_per_host == Some(0) {
+        anyhow::bail!("max_concurrency_per_host must be at least 1");
+    }
+    let fault_injection = match &cfg.fault_injection {
+        Some(faults) if !(0.0..=1.0).contains(&faults.rate) => {
+            anyhow::bail!(
+                "fault_injection.rate must be between 0.0 and 1.0, got {}",
+                faults.rate
+            );
+        }
+        Some(_) if !cfg.allow_fault_injection => {
+            warn!(
+                "fault_injection is configured but --allow-fault-injection is not set; ignoring it"
+            );
+            None
+        }
+        Some(faults) => {
+            warn!(rate = faults.rate, fault = ?faults.fault, "fault injection enabled; matching checks will fail on purpose");
+            Some(faults)
+        }
+        None => None,
+    };
+    let trust_audit = (cfg.report_cert_validation || cfg.fail_on_untrusted_cert)
+        .then(|| TrustAudit::new(cfg))
+        .transpose()?;
+    for ep in &mut endpoints {
+        if http_method(&ep.method).is_none() {
+            anyhow::bail!(
+                "endpoint {}: unsupported method `{}` (expected GET, HEAD, POST, PUT, PATCH, \
+                 DELETE or OPTIONS)",
+                redact_url(&ep.url),
+                ep.method
+            );
+        }
+        ep.method.make_ascii_uppercase();
+        if let Some(faults) = fault_injection
+            && (faults.endpoints.is_empty() || faults.endpoints.contains(&ep.url))
+        {
+            ep.fault_injection = Some(faults.clone());
+        }
+        if ep.kind == CheckKind::Dns {
+            if cfg!(not(feature = "dns")) {
+                anyhow::bail!("`kind: dns` requires building with the `dns` feature");
+            }
+            if ep.dns.is_none() {
+                anyhow::bail!(
+                    "endpoint {}: `kind: dns` requires a `dns` expectation",
+                    redact_url(&ep.url)
+                );
+            }
+        }
+        if ep.http_version == Some(HttpVersion::Http3) {
+            if cfg!(not(feature = "http3")) {
+                anyhow::bail!("`http_version: http3` requires building with the `http3` feature");
+            }
+            if !ep.url.starts_with("https://") {
+                anyhow::bail!(
+                    "endpoint {}: `http_version: http3` requires an https URL",
+                    redact_url(&ep.url)
+                );
+            }
+            if ep.sni.is_some() {
+                anyhow::bail!(
+                    "endpoint {}: `sni` is not supported with `http_version: http3`",
+                    redact_url(&ep.url)
+                );
+            }
+        }
+        for url in std::iter::once(&ep.url).chain(ep.failover_urls.iter().flatten()) {
+            if let Some(zone) = ipv6_zone_id(url) {
+                anyhow::bail!(
+                    "endpoint {}: IPv6 zone IDs (%{}) are not supported in URLs; use a global \
+                     or unique-local address, or a hostname, instead of a link-local one",
+                    redact_url(url),
+                    zone
+                );
+            }
+        }
+        if ep.kind == CheckKind::RawTcp {
+            ep.raw_tcp_regex = raw_tcp::validate(ep)
+                .with_context(|| format!("endpoint {}", redact_url(&ep.url)))?;
+        }
+        if ep.kind == CheckKind::Tcp {
+            raw_tcp::validate_address(&ep.url)
+                .with_context(|| format!("endpoint {}", redact_url(&ep.url)))?;
+        }
+        if ep.base_backoff_ms.is_some() || ep.max_backoff_ms.is_some() {
+            let base = ep.base_backoff_ms.unwrap_or(cfg.base_backoff_ms);
+            let max = ep.max_backoff_ms.unwrap_or(cfg.max_backoff_ms);
+            if base > max {
+                anyhow::bail!(
+                    "endpoint {}: base_backoff_ms ({}) exceeds max_backoff_ms ({})",
+                    redact_url(&ep.url),
+                    base,
+                    max
+                );
+            }
+        }
+        if let Some(multiplier) = ep.retry_timeout_multiplier
+            && !(multiplier.is_finite() && multiplier >= 1.0)
+        {
+            anyhow::bail!(
+                "endpoint {}: retry_timeout_multiplier must be a number of at least 1, got {}",
+                redact_url(&ep.url),
+                multiplier
+            );
+        }
+        if let Some(weight) = ep.weight
+            && !(weight.is_finite() && weight >= 0.0)
+        {
+            anyhow::bail!(
+                "endpoint {}: weight must be a non-negative number",
+                redact_url(&ep.url)
+            );
+        }
+        if ep.kind == CheckKind::Http {
+            ep.trust_audit = trust_audit.clone();
+        } else if ep.auth.is_some() {
+            anyhow::bail!(
+                "endpoint {}: auth only applies to kind: http",
+                redact_url(&ep.url)
+            );
+        }
+        if ep.auth.is_some()
+            && Url::parse(&ep.url).is_ok_and(|u| !u.username().is_empty() || u.password().is_some())
+        {
+            // Both would be sent as Authorization headers
+            anyhow::bail!(
+                "endpoint {}: auth conflicts with credentials in the url",
+                redact_url(&ep.url)
+            );
+        }
+        if ep.capture_response_header.is_none() {
+            ep.capture_response_header = cfg.capture_response_header.clone();
+        }
+        ep.report_degraded.get_or_insert(cfg.report_degraded);
+        ep.connection_close.get_or_insert(cfg.connection_close);
+        if ep.max_latency_ms.is_none() {
+            ep.max_latency_ms = cfg.max_latency_ms;
+        }
+        if ep.max_body_bytes.is_none() {
+            ep.max_body_bytes = cfg.max_body_bytes;
+        }
+        if ep.max_body_bytes == Some(0) {
+            anyhow::bail!(
+                "endpoint {}: max_body_bytes must be at least 1",
+                redact_url(&ep.url)
+            );
+        }
+        if ep.verify_keepalive && ep.connection_close == Some(true) {
+            anyhow::bail!(
+                "endpoint {}: verify_keepalive conflicts with connection_close",
+                redact_url(&ep.url)
+            );
+        }
+        // The follow-up request repeats the check, so it must be safe to send twice
+        if ep.verify_keepalive && matches!(ep.method.as_str(), "POST" | "PUT" | "PATCH" | "DELETE")
+        {
+            anyhow::bail!(
+                "endpoint {}: verify_keepalive resends the request, which {} is not safe for",
+                redact_url(&ep.url),
+                ep.method
+            );
+        }
+        if cfg.timeout_multiplier.is_some()
+            && let Some(timeout_ms) = ep.timeout_ms
+        {
+            let scaled = scaled_timeout_ms(cfg, timeout_ms);
+            info!(endpoint = %redact_url(&ep.url), timeout_ms = scaled, "scaled endpoint timeout");
+            ep.timeout_ms = Some(scaled);
+        }
+        if ep.local_address.is_none() {
+            ep.local_address = cfg.local_address;
+        }
+        if ep.user_agent.is_some()
+            && ep.headers.as_ref().is_some_and(|hs| {
+                hs.keys()
+                    .any(|k| k.eq_ignore_ascii_case(reqwest::header::USER_AGENT.as_str()))
+            })
+        {
+            warn!(
+                endpoint = %redact_url(&ep.url),
+                "both `user_agent` and a User-Agent header are set; using `user_agent`"
+            );
+        }
+        if ep.accept.is_some()
+            && ep.headers.as_ref().is_some_and(|hs| {
+                hs.keys()
+                    .any(|k| k.eq_ignore_ascii_case(reqwest::header::ACCEPT.as_str()))
+            })
+        {
+            warn!(
+                endpoint = %redact_url(&ep.url),
+                "both `accept` and an Accept header are set; using `accept`"
+            );
+        }
+        if let Some(sni) = &ep.sni {
+            if !ep.url.starts_with("https://") {
+                anyhow::bail!("endpoint {}: `sni` needs an https URL", redact_url(&ep.url));
+            }
+            if ep.failover_urls.is_some() {
+                anyhow::bail!(
+                    "endpoint {}: `sni` cannot be combined with `failover_urls`",
+                    redact_url(&ep.url)
+                );
+            }
+            if !matches!(url::Host::parse(sni), Ok(url::Host::Domain(_))) {
+                anyhow::bail!(
+                    "endpoint {}: `sni` must be a DNS name, got {}",
+                    redact_url(&ep.url),
+                    sni
+                );
+            }
+        }
+        match &ep.expected_final_url {
+            Some(ExpectedFinalUrl::Exact(url)) => {
+                Url::parse(url).with_context(|| {
+                    format!(
+                        "endpoint {}: invalid expected_final_url {}",
+                        redact_url(&ep.url),
+                        url
+                    )
+                })?;
+            }
+            Some(ExpectedFinalUrl::Regex { regex }) => {
+                let compiled = regex::Regex::new(regex).with_context(|| {
+                    format!(
+                        "endpoint {}: invalid expected_final_url regex {}",
+                        redact_url(&ep.url),
+                        regex
+                    )
+                })?;
+                ep.final_url_regex = Some(compiled);
+            }
+            None => {}
+        }
+        if ep.cert_requirements.is_some() && !ep.url.starts_with("https://") {
+            anyhow::bail!(
+                "endpoint {}: `cert_requirements` needs an https URL",
+                redact_url(&ep.url)
+            );
+        }
+        if let Some(path) = ep.body_file.take() {
+            if ep.body.is_some() {
+                anyhow::bail!(
+                    "endpoint {}: `body` and `body_file` are mutually exclusive",
+                    redact_url(&ep.url)
+                );
+            }
+            let body = fs::read_to_string(&path).with_context(|| {
+                format!(
+                    "endpoint {}: failed to read body_file {}",
+                    redact_url(&ep.url),
+                    path
+                )
+            })?;
+            if ep.content_type.is_none() {
+                ep.content_type = content_type_for_path(&path).map(str::to_string);
+            }
+            ep.body = Some(body);
+        }
+        if let Some(path) = &ep.expected_body_schema {
+            let schema = BodySchema::from_file(path).with_context(|| {
+                format!("endpoint {}: expected_body_schema", redact_url(&ep.url))
+            })?;
+            ep.body_schema = Some(schema);
+        }
+        if let Some(regex) = &ep.body_matches {
+            let compiled = regex::Regex::new(regex).with_context(|| {
+                format!(
+                    "endpoint {}: invalid body_matches regex {}",
+                    redact_url(&ep.url),
+                    regex
+                )
+            })?;
+            ep.body_regex = Some(compiled);
+        }
+        if let Some(label) = &ep.expect_charset
+            && encoding_rs::Encoding::for_label(label.as_bytes()).is_none()
+        {
+            anyhow::bail!(
+                "endpoint {}: unknown expect_charset `{}`",
+                redact_url(&ep.url),
+                label
+            );
+        }
+        if let Some(path) = &ep.golden_file {
+            let ignore = ep.golden_ignore_paths.as_deref().unwrap_or_default();
+            let golden = GoldenBody::from_file(path, ignore)
+                .with_context(|| format!("endpoint {}: golden_file", redact_url(&ep.url)))?;
+            ep.golden = Some(golden);
+        } else if ep.golden_ignore_paths.is_some() {
+            anyhow::bail!(
+                "endpoint {}: `golden_ignore_paths` needs a `golden_file`",
+                redact_url(&ep.url)
+            );
+        }
+        if let Some(metric) = &ep.expect_metric {
+            metric
+                .validate()
+                .with_context(|| format!("endpoint {}", redact_url(&ep.url)))?;
+        }
+    }
+    Ok(endpoints)
+}
+
+fn content_type_for_path(path: &str) -> Option<&'static str> {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_ascii_lowercase())?;
+    match ext.as_str() {
+        "json" => Some("application/json"),
+        "xml" => Some("application/xml"),
+        "yaml" | "yml" => Some("application/yaml"),
+        "txt" => Some("text/plain"),
+        "html" | "htm" => Some("text/html"),
+        _ => None,
+    }
+}
+
+/// Applies `policy` to endpoints describing the same request.
+///
+/// Endpoints are the same if kind, method, URL, headers (names compared case-insensitively),
+/// auth and body all match; ones differing only in e.g. `retries` or `timeout_ms` are duplicates.
+fn handle_duplicates(
+    endpoints: Vec<EndpointConfig>,
+    policy: DuplicatePolicy,
+) -> Result<Vec<EndpointConfig>> {
+    use std::collections::HashMap;
+    let mut first_seen: HashMap<_, usize> = HashMap::new();
+    let mut kept = Vec::with_capacity(endpoints.len());
+    for (i, ep) in endpoints.into_iter().enumerate() {
+        let mut headers: Vec<(String, String)> = ep
+            .headers
+            .iter()
+            .flatten()
+            .map(|(k, v)| (k.to_ascii_lowercase(), v.clone()))
+            .collect();
+        headers.sort();
+        let identity = (
+            ep.kind,
+            ep.method.to_ascii_uppercase(),
+            ep.url.clone(),
+            headers,
+            ep.auth.clone(),
+            ep.body.clone(),
+            ep.body_file.clone(),
+            ep.sni.clone(),
+        );
+        match first_seen.get(&identity) {
+            Some(&first) => {
+                let message = format!(
+                    "{} {} is configured more than once (entries {} and {})",
+                    identity.1,
+                    redact_url(&ep.url),
+                    first + 1,
+                    i + 1
+                );
+                match policy {
+                    DuplicatePolicy::Warn => {
+                        warn!("duplicate endpoint: {}", message);
+                        kept.push(ep);
+                    }
+                    DuplicatePolicy::Dedup => info!("dropping duplicate endpoint: {}", message),
+                    DuplicatePolicy::Error => anyhow::bail!("duplicate endpoint: {}", message),
+                }
+            }
+            None => {
+                first_seen.insert(identity, i);
+                kept.push(ep);
+            }
+        }
+    }
+    Ok(kept)
+}
+
+pub fn build_client(cfg: &Config) -> Result<Client> {
+    build_client_from(cfg, cfg.local_address, None, false)
+}
+
+/// Like [`build_client`], but reads the CA bundles (`ca_bundle_path`, `ca_bundle_dir`) and
+/// client certificate on tokio's blocking pool instead of the calling task's thread.
+pub async fn build_client_async(cfg: &Config) -> Result<Client> {
+    let cfg = cfg.clone();
+    tokio::task::spawn_blocking(move || build_client(&cfg))
+        .await
+        .context("client builder task failed")?
+}
+
+/// Builds a client that also applies the endpoint's own `local_address`, `sni` and
+/// `http_version`, which [`build_client`] ignores; the runners keep one per distinct setting.
+pub fn build_endpoint_client(cfg: &Config, ep: &EndpointConfig) -> Result<Client> {
+    let sni_alias = match &ep.sni {
+        Some(sni) => Some((sni.clone(), url_host(&ep.url)?)),
+        None => None,
+    };
+    build_client_from(
+        cfg,
+        ep.local_address.or(cfg.local_address),
+        sni_alias,
+        ep.http_version == Some(HttpVersion::Http3),
+    )
+}
+
+/// Builds the client for `cfg` with requests bound to `local_address`.
+///
+/// With `sni_alias`, the first name is resolved as the second host (see `sni`). With `http3`,
+/// requests go over QUIC without trying HTTP/1.1 or HTTP/2 first.
+fn build_client_from(
+    cfg: &Config,
+    local_address: Option<IpAddr>,
+    sni_alias: Option<(String, String)>,
+    http3: bool,
+) -> Result<Client> {
+    let mut builder = Client::builder()
+        .user_agent(&cfg.user_agent)
+        .timeout(Duration::from_millis(scaled_timeout_ms(
+            cfg,
+            cfg.request_timeout_ms,
+        )))
+        .danger_accept_invalid_certs(cfg.danger_accept_invalid_certs)
+        .dns_resolver(Arc::new(timing::TimedResolver {
+            cache: cfg
+                .dns_stale_tolerance_sec
+                .map(|sec| Arc::new(DnsCache::new(Duration::from_secs(sec)))),
+            alias: sni_alias,
+        }))
+        .connector_layer(timing::ConnectTimingLayer)
+        .redirect(redirect::policy())
+        .local_address(local_address);
+    // Endpoints may also inherit a policy from a template
+    let cert_policies = cfg
+        .endpoints
+        .iter()
+        .flatten()
+        .any(|ep| ep.cert_requirements.is_some())
+        || cfg
+            .templates
+            .values()
+            .any(|t| t.cert_requirements.is_some());
+    if cert_policies {
+        // Exposes the peer certificate on responses
+        builder = builder.tls_info(true);
+    }
+    if let Some(version) = cfg.min_tls_version {
+        builder = builder.min_tls_version(match version {
+            TlsVersion::Tls12 => reqwest::tls::Version::TLS_1_2,
+            TlsVersion::Tls13 => reqwest::tls::Version::TLS_1_3,
+        });
+    }
+    let mut ca_files: Vec<PathBuf> = cfg.ca_bundle_path.iter().map(PathBuf::from).collect();
+    if let Some(dir) = &cfg.ca_bundle_dir {
+        ca_files.extend(ca_bundle_dir_files(dir)?);
+    }
+    for path in &ca_files {
+        for cert in read_ca_bundle(path)? {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    if let Some(identity) = read_client_identity(cfg)? {
+        builder = builder.identity(identity);
+    }
+    if http3 {
+        #[cfg(feature = "http3")]
+        {
+            builder = builder.http3_prior_knowledge();
+        }
+        #[cfg(not(feature = "http3"))]
+        anyhow::bail!("`http_version: http3` requires building with the `http3` feature");
+    }
+    let client = builder.build().context("failed to build reqwest client")?;
+    Ok(client)
+}
+
+/// The `.pem` and `.crt` files in `dir`, sorted by name.
+fn ca_bundle_dir_files(dir: &str) -> Result<Vec<PathBuf>> {
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("failed to read ca bundle dir {}", dir))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry
+            .with_context(|| format!("failed to read ca bundle dir {}", dir))?
+            .path();
+        let is_cert = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("pem") || ext.eq_ignore_ascii_case("crt"));
+        if is_cert && path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Every certificate in a PEM file (a bundle may concatenate several).
+fn read_ca_bundle(path: &Path) -> Result<Vec<reqwest::Certificate>> {
+    let pem = fs::read(path)
+        .with_context(|| format!("failed to read ca bundle at {}", path.display()))?;
+    let certs = reqwest::Certificate::from_pem_bundle(&pem)
+        .with_context(|| format!("invalid PEM in ca bundle {}", path.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("no certificates found in ca bundle {}", path.display());
+    }
+    Ok(certs)
+}
+
+/// The `client_cert_path`/`client_key_path` pair as a TLS client identity, if configured.
+fn read_client_identity(cfg: &Config) -> Result<Option<reqwest::Identity>> {
+    let (cert_path, key_path) = match (&cfg.client_cert_path, &cfg.client_key_path) {
+        (Some(cert), Some(key)) => (cert, key),
+        (None, None) => return Ok(None),
+        (Some(_), None) => anyhow::bail!("client_cert_path is set without client_key_path"),
+        (None, Some(_)) => anyhow::bail!("client_key_path is set without client_cert_path"),
+    };
+    let mut pem = fs::read(cert_path)
+        .with_context(|| format!("failed to read client certificate at {}", cert_path))?;
+    pem.push(b'\n');
+    pem.extend(
+        fs::read(key_path).with_context(|| format!("failed to read client key at {}", key_path))?,
+    );
+    let identity = reqwest::Identity::from_pem(&pem).with_context(|| {
+        format!(
+            "invalid client certificate {} or key {}",
+            cert_path, key_path
+        )
+    })?;
+    Ok(Some(identity))
+}
+
+/// One client per source address; endpoints without their own `local_address` share the
+/// default client.
+#[derive(Debug, Clone)]
+struct Clients {
+    default: Client,
+    by_source: std::collections::HashMap<IpAddr, Client>,
+    /// Keyed by source address, server name and URL host, so connections are never pooled
+    /// across endpoints that share a name but not a target
+    by_sni: std::collections::HashMap<(Option<IpAddr>, String, String), Client>,
+    /// `http_version: http3` clients, keyed by source address
+    http3: std::collections::HashMap<Option<IpAddr>, Client>,
+}
+
+impl Clients {
+    fn build(cfg: &Config, endpoints: &[EndpointConfig]) -> Result<Self> {
+        let mut by_source = std::collections::HashMap::new();
+        for addr in endpoints.iter().filter_map(|ep| ep.local_address) {
+            if Some(addr) != cfg.local_address && !by_source.contains_key(&addr) {
+                by_source.insert(addr, build_client_from(cfg, Some(addr), None, false)?);
+            }
+        }
+        let mut by_sni = std::collections::HashMap::new();
+        for ep in endpoints {
+            if let Some(key) = sni_client_key(ep)
+                && !by_sni.contains_key(&key)
+            {
+                by_sni.insert(key, build_endpoint_client(cfg, ep)?);
+            }
+        }
+        let mut http3 = std::collections::HashMap::new();
+        for ep in endpoints {
+            if ep.http_version == Some(HttpVersion::Http3) && !http3.contains_key(&ep.local_address)
+            {
+                http3.insert(ep.local_address, build_endpoint_client(cfg, ep)?);
+            }
+        }
+        Ok(Self {
+            default: build_client(cfg)?,
+            by_source,
+            by_sni,
+            http3,
+        })
+    }
+
+    fn for_endpoint(&self, ep: &EndpointConfig) -> &Client {
+        if ep.http_version == Some(HttpVersion::Http3)
+            && let Some(client) = self.http3.get(&ep.local_address)
+        {
+            return client;
+        }
+        if let Some(client) = sni_client_key(ep).and_then(|key| self.by_sni.get(&key)) {
+            return client;
+        }
+        ep.local_address
+            .and_then(|addr| self.by_source.get(&addr))
+            .unwrap_or(&self.default)
+    }
+}
+
+fn sni_client_key(ep: &EndpointConfig) -> Option<(Option<IpAddr>, String, String)> {
+    let sni = ep.sni.clone()?;
+    Some((ep.local_address, sni, url_host(&ep.url).ok()?))
+}
+
+/// Sets `health_path` as the path of every HTTP endpoint URL (failover URLs included) that
+/// has no path beyond `/`.
+fn apply_health_path(endpoints: &mut [EndpointConfig], health_path: &str) -> Result<()> {
+    if health_path.trim_matches('/').is_empty() {
+        anyhow::bail!("health_path must not be empty");
+    }
+    let path = format!("/{}", health_path.trim_start_matches('/'));
+    for ep in endpoints.iter_mut().filter(|ep| ep.kind == CheckKind::Http) {
+        for url in std::iter::once(&mut ep.url).chain(ep.failover_urls.iter_mut().flatten()) {
+            let mut parsed =
+                Url::parse(url).with_context(|| format!("invalid url {}", redact_url(url)))?;
+            if parsed.path() == "/" && !parsed.cannot_be_a_base() {
+                parsed.set_path(&path);
+                *url = parsed.to_string();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Host of `url` as looked up (IPv6 literals without brackets).
+fn url_host(url: &str) -> Result<String> {
+    let parsed = Url::parse(url).with_context(|| format!("invalid url {}", redact_url(url)))?;
+    let host = parsed
+        .host_str()
+        .with_context(|| format!("url {} has no host", redact_url(url)))?;
+    Ok(host.trim_matches(['[', ']']).to_string())
+}
+
+/// `target` with its host replaced by `sni`, so TLS uses it as the server name and the
+/// certificate is verified against it.
+fn sni_target(target: &str, sni: &str) -> Result<String, String> {
+    let mut url = Url::parse(target).map_err(|e| format!("invalid url: {}", e))?;
+    url.set_host(Some(sni))
+        .map_err(|e| format!("invalid sni {}: {}", sni, e))?;
+    Ok(url.into())
+}
+
+/// Zone ID of a bracketed IPv6 host such as `[fe80::1%eth0]` (or the RFC 6874 `%25eth0`).
+///
+/// The `url` crate rejects these with a bare "invalid IPv6 address", and a literal address is
+/// connected to without a scope anyway, so they are caught when the config is loaded.
+fn ipv6_zone_id(url: &str) -> Option<&str> {
+    let authority = url.split_once("://")?.1.split(['/', '?', '#']).next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let inner = host.strip_prefix('[')?.split(']').next()?;
+    let zone = inner.split_once('%')?.1;
+    Some(
+        zone.strip_prefix("25")
+            .filter(|z| !z.is_empty())
+            .unwrap_or(zone),
+    )
+}
+
+fn redact_url(input: &str) -> String {
+    if let Ok(u) = Url::parse(input) {
+        let mut redacted = u.clone();
+        redacted.set_query(None);
+        let _ = redacted.set_password(None);
+        return redacted.to_string();
+    }
+    // Unparseable (e.g. an IPv6 zone ID): cut the query and any userinfo password by hand
+    let without_query = input.split('?').next().unwrap_or_default();
+    let Some((scheme, rest)) = without_query.split_once("://") else {
+        return without_query.to_string();
+    };
+    let authority_len = rest.find(['/', '#']).unwrap_or(rest.len());
+    match rest[..authority_len].rsplit_once('@') {
+        Some((userinfo, host)) => {
+            let user = userinfo.split(':').next().unwrap_or_default();
+            format!("{}://{}@{}{}", scheme, user, host, &rest[authority_len..])
+        }
+        None => without_query.to_string(),
+    }
+}
+
+fn status_matches_expected(status: StatusCode, expected: &Option<ExpectedStatus>) -> bool {
+    if let Some(e) = expected {
+        e.matches(status.as_u16())
+    } else {
+        status.is_success()
+    }
+}
+
+/// Checks the URL a response was served from against `expected_final_url`, if set.
+fn final_url_matches(ep: &EndpointConfig, url: &Url) -> Option<Result<(), String>> {
+    let expected = ep.expected_final_url.as_ref()?;
+    let matched = match expected {
+        ExpectedFinalUrl::Exact(exact) => Url::parse(exact).is_ok_and(|exact| exact == *url),
+        ExpectedFinalUrl::Regex { .. } => ep
+            .final_url_regex
+            .as_ref()
+            .is_none_or(|re| re.is_match(url.as_str())),
+    };
+    Some(if matched {
+        Ok(())
+    } else {
+        Err(format!(
+            "ended at {}, expected {}",
+            redact_url(url.as_str()),
+            expected
+        ))
+    })
+}
+
+/// The first of `forbidden_headers` present in the response, as configured.
+fn forbidden_header_present<'a>(
+    ep: &'a EndpointConfig,
+    headers: &reqwest::header::HeaderMap,
+) -> Option<&'a str> {
+    ep.forbidden_headers
+        .iter()
+        .flatten()
+        .find(|name| headers.contains_key(name.as_str()))
+        .map(String::as_str)
+}
+
+/// Whether the response's Content-Type is one of the media ranges in `accept`.
+fn negotiated(accept: &str, headers: &reqwest::header::HeaderMap) -> Result<(), String> {
+    let Some(content_type) = headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return Err(format!("no Content-Type in response, expected {}", accept));
+    };
+    let media_type = |value: &str| {
+        value
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+    };
+    let actual = media_type(content_type);
+    let matches = accept.split(',').map(media_type).any(|range| {
+        range == "*/*"
+            || range == actual
+            || range
+                .strip_suffix("/*")
+                .is_some_and(|kind| actual.split('/').next() == Some(kind))
+    });
+    if matches {
+        Ok(())
+    } else {
+        Err(format!(
+            "Content-Type {} does not match Accept {}",
+            actual, accept
+        ))
+    }
+}
+
+/// Checks `ep` once with `client`.
+///
+/// An endpoint with `sni` needs the client from [`build_endpoint_client`]; any other client
+/// fails the check with `CONFIG_ERROR` rather than connecting to the server name.
+pub async fn check_endpoint_once(
+    client: &Client,
+    ep: &EndpointConfig,
+    default_timeout_ms: u64,
+) -> CheckOutcome {
+    check_target_once(client, ep, &ep.url, default_timeout_ms).await
+}
+
+/// Checks `ep` against `target`, which is either its primary URL or one of its failover URLs.
+async fn check_target_once(
+    client: &Client,
+    ep: &EndpointConfig,
+    target: &str,
+    default_timeout_ms: u64,
+) -> CheckOutcome {
+    if let Some(faults) = &ep.fault_injection
+        && rand::random_bool(faults.rate)
+    {
+        return inject_fault(ep, faults.fault, default_timeout_ms).await;
+    }
+    if ep.kind == CheckKind::Dns {
+        #[cfg_attr(not(feature = "dns"), allow(unused_mut))]
+        let mut outcome = CheckOutcome::new(redact_url(&ep.url), HealthStatus::Up);
+        #[cfg(feature = "dns")]
+        let verdict = match dns::system_resolver() {
+            Ok(resolver) => {
+                dns::check_dns_target(resolver, ep, target, default_timeout_ms, &mut outcome).await
+            }
+            Err(e) => Err((ReasonCode::DnsFailure, e)),
+        };
+        #[cfg(not(feature = "dns"))]
+        let verdict = Err((
+            ReasonCode::ConfigError,
+            "dns checks require building with the `dns` feature".to_string(),
+        ));
+        return record_verdict(ep, outcome, verdict.map_err(down));
+    }
+    if ep.kind == CheckKind::RawTcp {
+        let mut outcome = CheckOutcome::new(redact_url(&ep.url), HealthStatus::Up);
+        let timeout = Duration::from_millis(ep.timeout_ms.unwrap_or(default_timeout_ms));
+        let verdict = raw_tcp::check(ep, target, timeout, &mut outcome).await;
+        return record_verdict(ep, outcome, verdict.map_err(down));
+    }
+    if ep.kind == CheckKind::Tcp {
+        let mut outcome = CheckOutcome::new(redact_url(&ep.url), HealthStatus::Up);
+        let timeout = Duration::from_millis(ep.timeout_ms.unwrap_or(default_timeout_ms));
+        let verdict = raw_tcp::connect(target, timeout, &mut outcome).await;
+        return record_verdict(ep, outcome, verdict.map_err(down));
+    }
+    let sni_url = match &ep.sni {
+        Some(sni) => match sni_target(target, sni) {
+            Ok(url) => Some(url),
+            Err(reason) => {
+                let outcome = CheckOutcome::new(redact_url(&ep.url), HealthStatus::Up);
+                return record_verdict(ep, outcome, Err(down((ReasonCode::ConfigError, reason))));
+            }
+        },
+        None => None,
+    };
+    // prepare_endpoints rejects unsupported methods; unprepared endpoints may still carry one
+    let Some(method) = http_method(&ep.method) else {
+        let outcome = CheckOutcome::new(redact_url(&ep.url), HealthStatus::Up);
+        let reason = format!("unsupported method `{}`", ep.method);
+        return record_verdict(ep, outcome, Err(down((ReasonCode::ConfigError, reason))));
+    };
+    let request_url = sni_url.as_deref().unwrap_or(target);
+    let limit = ep.max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES);
+    // Reserved before sending so the wait is not part of the request timeout, and held
+    // until the buffered body has been checked
+    let mut reserved = match &ep.body_budget {
+        Some(budget) if reads_body(ep) => Some(budget.reserve(limit).await),
+        _ => None,
+    };
+    let mut start = Instant::now();
+    let mut req = client.request(method, request_url);
+    if let Some(ct) = &ep.content_type {
+        let overridden = ep.headers.as_ref().is_some_and(|hs| {
+            hs.keys()
+                .any(|k| k.eq_ignore_ascii_case(reqwest::header::CONTENT_TYPE.as_str()))
+        });
+        if !overridden {
+            req = req.header(reqwest::header::CONTENT_TYPE, ct);
+        }
+    }
+    if let Some(hs) = &ep.headers {
+        for (k, v) in hs {
+            if ep.accept.is_some() && k.eq_ignore_ascii_case(reqwest::header::ACCEPT.as_str()) {
+                continue;
+            }
+            if ep.user_agent.is_some()
+                && k.eq_ignore_ascii_case(reqwest::header::USER_AGENT.as_str())
+            {
+                continue;
+            }
+            if ep.connection_close == Some(true)
+                && k.eq_ignore_ascii_case(reqwest::header::CONNECTION.as_str())
+            {
+                continue;
+            }
+            if ep.anti_cache
+                && (k.eq_ignore_ascii_case(reqwest::header::CACHE_CONTROL.as_str())
+                    || k.eq_ignore_ascii_case(reqwest::header::PRAGMA.as_str()))
+            {
+                continue;
+            }
+            if ep.auth.is_some() && k.eq_ignore_ascii_case(reqwest::header::AUTHORIZATION.as_str())
+            {
+                continue;
+            }
+            req = req.header(k, v);
+        }
+    }
+    if let Some(auth) = &ep.auth {
+        match auth.header_value() {
+            Ok(value) => req = replace_header(req, reqwest::header::AUTHORIZATION, value),
+            Err(e) => {
+                let outcome = CheckOutcome::new(redact_url(&ep.url), HealthStatus::Up);
+                return record_verdict(
+                    ep,
+                    outcome,
+                    Err(down((ReasonCode::ConfigError, format!("{:#}", e)))),
+                );
+            }
+        }
+    }
+    if let Some(ua) = &ep.user_agent {
+        // Replaces the client-wide User-Agent and any from `headers`, skipped above
+        req = req.header(reqwest::header::USER_AGENT, ua);
+    }
+    if let Some(accept) = &ep.accept {
+        // Replaces any Accept from `headers`, skipped above
+        req = req.header(reqwest::header::ACCEPT, accept);
+    }
+    if ep.connection_close == Some(true) {
+        // hyper drops the connection after the response instead of returning it to the pool;
+        // a Connection from `headers` is skipped above
+        req = req.header(reqwest::header::CONNECTION, "close");
+    }
+    if ep.anti_cache {
+        // A unique query makes CDNs treat each check as a cache miss and forward it to the origin;
+        // Cache-Control and Pragma from `headers` are skipped above
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        req = req
+            .query(&[("_hc", now_ms)])
+            .header(reqwest::header::CACHE_CONTROL, "no-cache")
+            .header(reqwest::header::PRAGMA, "no-cache");
+    }
+    if let Some(body) = &ep.body {
+        req = req.body(body.clone());
+    }
+    req = req.timeout(Duration::from_millis(
+        ep.timeout_ms.unwrap_or(default_timeout_ms),
+    ));
+    let mut outcome = CheckOutcome::new(redact_url(&ep.url), HealthStatus::Up);
+    outcome.sni = ep.sni.clone();
+    let keepalive_probe = if ep.verify_keepalive {
+        req.try_clone()
+    } else {
+        None
+    };
+    let mut connection_retries = ep.connection_retries.unwrap_or(0);
+    let (sent, timing, chain) = loop {
+        // Bodies are in memory, so the clone only fails for streaming bodies
+        let retry = (connection_retries > 0).then(|| req.try_clone()).flatten();
+        let send = timing::with_sni(ep.sni.as_deref(), req.send());
+        let ((sent, timing), chain) = redirect::recorded(timing::timed(send)).await;
+        match (sent, retry) {
+            (Err(e), Some(next)) if is_connection_failure(&e) => {
+                debug!(
+                    endpoint = %redact_url(target),
+                    reason = %normalize_reason(&e),
+                    "connection failed; re-sending immediately"
+                );
+                connection_retries -= 1;
+                req = next;
+                start = Instant::now();
+            }
+            (sent, _) => break (sent, timing, chain),
+        }
+    };
+    outcome.timing = timing;
+    outcome.redirect_chain = chain.iter().map(|url| redact_url(url.as_str())).collect();
+    // A request that failed before or after TLS (refused, timed out, ...) says nothing about
+    // the certificate, and a second handshake would only fail the same way
+    if let Some(audit) = &ep.trust_audit
+        && sent
+            .as_ref()
+            .err()
+            .is_none_or(|e| reason_code(e) == ReasonCode::TlsError)
+    {
+        let timeout = Duration::from_millis(ep.timeout_ms.unwrap_or(default_timeout_ms));
+        outcome.cert_trust = audit
+            .check(target, ep.sni.as_deref(), sent.is_ok(), timeout)
+            .await;
+    }
+    let verdict = match sent {
+        Ok(mut resp) => {
+            let status = resp.status();
+            let latency = start.elapsed();
+            outcome.last_http_status = Some(status);
+            outcome.remote_addr = resp.remote_addr();
+            if let Some(name) = &ep.capture_response_header {
+                outcome.correlation_id = resp
+                    .headers()
+                    .get(name)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string);
+            }
+            outcome.retry_after_ms = resp
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| retry_after::parse(v, SystemTime::now()))
+                .map(|wait| wait.as_millis().try_into().unwrap_or(u64::MAX));
+            let cert_check = ep.cert_requirements.as_ref().map(|req| {
+                match resp
+                    .extensions()
+                    .get::<reqwest::tls::TlsInfo>()
+                    .and_then(|info| info.peer_certificate())
+                {
+                    Some(der) => check_certificate(der, req),
+                    None => Err("no peer certificate to check against cert_requirements".into()),
+                }
+            });
+            let degraded_or_down = |code: ReasonCode, reason: DownReason| {
+                if ep.report_degraded == Some(true) {
+                    (code, HealthStatus::Degraded(reason.to_string()))
+                } else {
+                    (code, HealthStatus::Down(reason))
+                }
+            };
+            if ep.detect_scheme_downgrade && redirect::downgrades(&chain) {
+                Err(down((
+                    ReasonCode::Redirect,
+                    "redirect downgraded https→http".to_string(),
+                )))
+            } else if let Some(Err(reason)) = final_url_matches(ep, resp.url()) {
+                Err(down((ReasonCode::Redirect, reason)))
+            } else if let Some(Err(reason)) = cert_check {
+                // `check_certificate` reports expiry as "...: expired"
+                let code = if reason.ends_with("expired") {
+                    ReasonCode::CertExpired
+                } else {
+                    ReasonCode::CertPolicy
+                };
+                Err(down((code, reason)))
+            } else if let Some(CertTrust::Untrusted(reason)) = &outcome.cert_trust
+                && ep.trust_audit.as_ref().is_some_and(|audit| audit.fatal)
+            {
+                Err(down((
+                    ReasonCode::CertUntrusted,
+                    format!("untrusted certificate: {}", reason),
+                )))
+            } else if ep.http_version == Some(HttpVersion::Http3)
+                && resp.version() != reqwest::Version::HTTP_3
+            {
+                Err(down((
+                    ReasonCode::ProtocolMismatch,
+                    format!("served over {:?}, expected HTTP/3", resp.version()),
+                )))
+            } else if let Some(accept) = &ep.accept
+                && status == StatusCode::NOT_ACCEPTABLE
+            {
+                Err(down((
+                    ReasonCode::HttpStatus,
+                    format!("HTTP 406 Not Acceptable: server cannot produce {}", accept),
+                )))
+            } else if !status_matches_expected(status, &ep.expected_status) {
+                Err(degraded_or_down(
+                    ReasonCode::HttpStatus,
+                    DownReason::Http(status),
+                ))
+            } else if let Some(name) = forbidden_header_present(ep, resp.headers()) {
+                Err(down((
+                    ReasonCode::HeaderMismatch,
+                    format!("forbidden header {} present", name),
+                )))
+            } else if let Some(Err(reason)) = ep
+                .accept
+                .as_deref()
+                .map(|accept| negotiated(accept, resp.headers()))
+            {
+                Err(down((ReasonCode::HeaderMismatch, reason)))
+            } else {
+                if let (Some(permit), Some(len)) = (&mut reserved, resp.content_length()) {
+                    // Give back what the announced body does not need
+                    let needed = (len as usize).clamp(1, permit.num_permits());
+                    drop(permit.split(permit.num_permits() - needed));
+                }
+                let body = if reads_body(ep) {
+                    read_body(&mut resp, limit, start).await.map(Some)
+                } else {
+                    Ok(None)
+                };
+                match body {
+                    Ok(body) => {
+                        // An empty body has no first byte; fall back to time-to-headers
+                        let ttfb = body.as_ref().map(|body| body.ttfb.unwrap_or(latency));
+                        outcome.latency_ms = Some(latency.as_millis());
+                        outcome.ttfb_ms = ttfb.map(|ttfb| ttfb.as_millis());
+                        outcome.total_ms = body.as_ref().map(|body| body.total.as_millis());
+                        check_response(ep, body.as_ref(), latency, ttfb).and_then(|()| {
+                            match body
+                                .as_ref()
+                                .and_then(|body| soft_error_marker(ep, &body.bytes))
+                            {
+                                Some(marker) => Err(degraded_or_down(
+                                    ReasonCode::SoftError,
+                                    DownReason::Other(format!(
+                                        "soft error page: body contains \"{}\"",
+                                        marker
+                                    )),
+                                )),
+                                None => Ok(()),
+                            }
+                        })
+                    }
+                    Err(e) => {
+                        // The URL in reqwest's message may carry credentials in its query
+                        let e = e.without_url();
+                        outcome.raw_reason = Some(error_chain(&e));
+                        Err(down((
+                            reason_code(&e),
+                            format!("failed to read body: {}", normalize_reason(&e)),
+                        )))
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            let e = e.without_url();
+            outcome.raw_reason = Some(error_chain(&e));
+            Err(down((reason_code(&e), normalize_reason(&e))))
+        }
+    };
+    drop(reserved);
+    // Only a fully read response hands its connection back to the pool
+    if let (Ok(()), Some(probe)) = (&verdict, keepalive_probe) {
+        let broken = !connection_reused(probe).await;
+        if broken {
+            warn!(endpoint = %redact_url(target), "connection not reused; keep-alive broken");
+        }
+        outcome.keepalive_broken = Some(broken);
+    }
+    record_verdict(ep, outcome, verdict)
+}
+
+/// Sets `name` on the request, replacing any value already there (`header` appends).
+fn replace_header(
+    req: reqwest::RequestBuilder,
+    name: reqwest::header::HeaderName,
+    value: reqwest::header::HeaderValue,
+) -> reqwest::RequestBuilder {
+    req.headers(reqwest::header::HeaderMap::from_iter([(name, value)]))
+}
+
+/// Sends `req` and reports whether it went out over a pooled connection, i.e. without a
+/// connect. A failed request counts as not reused.
+async fn connection_reused(req: reqwest::RequestBuilder) -> bool {
+    let (sent, timing) = timing::timed(req.send()).await;
+    match sent {
+        Ok(resp) => {
+            // Drain the body so the probe leaves the pool as it found it
+            let _ = resp.bytes().await;
+            timing.connect_ms.is_none()
+        }
+        Err(e) => {
+            debug!(error = %normalize_reason(&e), "keep-alive probe failed");
+            false
+        }
+    }
+}
+
+/// Fails a check without sending anything, as `fault_injection` asks.
+async fn inject_fault(
+    ep: &EndpointConfig,
+    fault: InjectedFault,
+    default_timeout_ms: u64,
+) -> CheckOutcome {
+    let reason = match fault {
+        InjectedFault::Down => "injected fault",
+        InjectedFault::Timeout => {
+            let timeout_ms = ep.timeout_ms.unwrap_or(default_timeout_ms);
+            tokio::time::sleep(Duration::from_millis(timeout_ms)).await;
+            "timeout (injected fault)"
+        }
+    };
+    counter!("healthcheck_injected_faults_total").increment(1);
+    let outcome = CheckOutcome::new(redact_url(&ep.url), HealthStatus::Up);
+    record_verdict(
+        ep,
+        outcome,
+        Err(down((ReasonCode::InjectedFault, reason.to_string()))),
+    )
+}
+
+tokio::task_local! {
+    /// Set while a watch sweep runs within `metrics_warmup_iterations`
+    static WARMUP: ();
+}
+
+/// `method` label of the check metrics: the HTTP method, or the kind of a non-HTTP check.
+fn metric_method(ep: &EndpointConfig) -> &'static str {
+    match ep.kind {
+        CheckKind::Http => match http_method(&ep.method).unwrap_or(Method::GET) {
+            Method::HEAD => "HEAD",
+            Method::POST => "POST",
+            Method::PUT => "PUT",
+            Method::PATCH => "PATCH",
+            Method::DELETE => "DELETE",
+            Method::OPTIONS => "OPTIONS",
+            _ => "GET",
+        },
+        CheckKind::Dns => "DNS",
+        CheckKind::RawTcp | CheckKind::Tcp => "TCP",
+    }
+}
+
+/// A down verdict from a failure's code and reason.
+fn down((code, reason): (ReasonCode, String)) -> (ReasonCode, HealthStatus) {
+    (code, HealthStatus::Down(DownReason::new(code, reason)))
+}
+
+/// Applies a check verdict to `outcome` and records the up/degraded/down metrics.
+fn record_verdict(
+    ep: &EndpointConfig,
+    mut outcome: CheckOutcome,
+    verdict: Result<(), (ReasonCode, HealthStatus)>,
+) -> CheckOutcome {
+    let method = metric_method(ep);
+    match verdict {
+        Ok(()) => {
+            // Cold caches and connection setup would skew the distribution
+            if WARMUP.try_with(|_| ()).is_err() {
+                if let Some(latency) = outcome.latency_ms {
+                    histogram!(
+                        "healthcheck_latency_ms",
+                        "endpoint" => outcome.endpoint.clone(),
+                        "method" => method
+                    )
+                    .record(latency as f64);
+                }
+                if let Some(ttfb) = outcome.ttfb_ms {
+                    histogram!("healthcheck_ttfb_ms", "endpoint" => outcome.endpoint.clone())
+                        .record(ttfb as f64);
+                }
+            }
+            counter!(
+                "healthcheck_up_total",
+                "endpoint" => outcome.endpoint.clone(),
+                "method" => method
+            )
+            .increment(1);
+        }
+        Err((code, status)) => {
+            match status {
+                HealthStatus::Degraded(_) => counter!(
+                    "healthcheck_degraded_total",
+                    "endpoint" => outcome.endpoint.clone(),
+                    "method" => method,
+                    "reason_code" => code.as_str()
+                )
+                .increment(1),
+                _ => counter!(
+                    "healthcheck_down_total",
+                    "endpoint" => outcome.endpoint.clone(),
+                    "method" => method,
+                    "reason" => code.category(),
+                    "reason_code" => code.as_str()
+                )
+                .increment(1),
+            }
+            outcome.status = status;
+            outcome.reason_code = Some(code);
+            // An SLA violation keeps the measured latency, to show how far over it went
+            if code != ReasonCode::SlaViolation {
+                outcome.latency_ms = None;
+            }
+        }
+    }
+    outcome
+}
+
+/// Response body as read for assertions, with timing.
+struct ResponseBody {
+    /// Leading bytes of the body, at most the buffer limit
+    bytes: Vec<u8>,
+    /// Total body size in bytes, including anything beyond the buffer limit
+    size: usize,
+    /// Time from request start to the first body byte, if the body was non-empty
+    ttfb: Option<Duration>,
+    /// Time from request start to the end of the body
+    total: Duration,
+}
+
+/// Response assertions evaluated after the status matched; `Err` carries the code and down reason.
+fn check_response(
+    ep: &EndpointConfig,
+    body: Option<&ResponseBody>,
+    latency: Duration,
+    ttfb: Option<Duration>,
+) -> Result<(), (ReasonCode, HealthStatus)> {
+    if let Some(max) = ep.max_latency_ms
+        && latency.as_millis() > max as u128
+    {
+        return Err((
+            ReasonCode::SlaViolation,
+            HealthStatus::Down(DownReason::LatencyExceeded {
+                latency_ms: latency.as_millis(),
+                max_ms: max,
+            }),
+        ));
+    }
+    if let (Some(max), Some(ttfb)) = (ep.max_ttfb_ms, ttfb)
+        && ttfb.as_millis() > max as u128
+    {
+        return Err(down((
+            ReasonCode::SlaViolation,
+            format!("ttfb {}ms > {}ms", ttfb.as_millis(), max),
+        )));
+    }
+    match body {
+        Some(body) => {
+            body_matches(ep, body).map_err(|reason| down((ReasonCode::BodyMismatch, reason)))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Whether the check reads the response body: for body assertions and `max_ttfb_ms`, and
+/// so `verify_keepalive` finds the connection back in the pool.
+fn reads_body(ep: &EndpointConfig) -> bool {
+    ep.expect_empty_body
+        || ep.expected_body_size.is_some()
+        || ep.body_schema.is_some()
+        || ep.golden.is_some()
+        || ep.expect_metric.is_some()
+        || ep.expect_charset.is_some()
+        || ep.body_contains.is_some()
+        || ep.body_matches.is_some()
+        || ep.detect_soft_errors
+        || ep.max_ttfb_ms.is_some()
+        || ep.verify_keepalive
+}
+
+/// Body assertions of [`check_response`]; `Err` carries the down reason.
+fn body_matches(ep: &EndpointConfig, body: &ResponseBody) -> Result<(), String> {
+    if ep.expect_empty_body && body.size > 0 {
+        return Err(format!("expected empty body, got {} bytes", body.size));
+    }
+    if let Some(range) = ep.expected_body_size
+        && !range.contains(body.size)
+    {
+        return Err(format!(
+            "body {} bytes outside expected {}",
+            body.size, range
+        ));
+    }
+    if let Some(schema) = &ep.body_schema {
+        if body.size > body.bytes.len() {
+            return Err(format!(
+                "body exceeds {} bytes, cannot validate against schema",
+                body.bytes.len()
+            ));
+        }
+        schema.check(&body.bytes)?;
+    }
+    if let Some(golden) = &ep.golden {
+        if body.size > body.bytes.len() {
+            return Err(format!(
+                "body exceeds {} bytes, cannot compare to golden file",
+                body.bytes.len()
+            ));
+        }
+        golden.check(&body.bytes)?;
+    }
+    if ep.body_contains.is_some() || ep.body_regex.is_some() {
+        let text = String::from_utf8_lossy(&body.bytes);
+        let within = if body.size > body.bytes.len() {
+            format!(" in the first {} bytes", body.bytes.len())
+        } else {
+            String::new()
+        };
+        if let Some(needle) = &ep.body_contains
+            && !text.contains(needle.as_str())
+        {
+            return Err(format!("body mismatch: {:?} not found{}", needle, within));
+        }
+        if let Some(re) = &ep.body_regex
+            && !re.is_match(&text)
+        {
+            return Err(format!(
+                "body mismatch: no match for /{}/{}",
+                re.as_str(),
+                within
+            ));
+        }
+    }
+    if let Some(metric) = &ep.expect_metric {
+        metric.check(&body.bytes, body.size > body.bytes.len())?;
+    }
+    if let Some(encoding) = ep
+        .expect_charset
+        .as_deref()
+        .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+    {
+        check_charset(encoding, &body.bytes, body.size > body.bytes.len())?;
+    }
+    Ok(())
+}
+
+/// Fails on the first byte sequence in `body` that is invalid in `encoding`.
+///
+/// A `truncated` body may end in the middle of a character, which is not an error.
+fn check_charset(
+    encoding: &'static encoding_rs::Encoding,
+    body: &[u8],
+    truncated: bool,
+) -> Result<(), String> {
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut text = String::with_capacity(
+        decoder
+            .max_utf8_buffer_length_without_replacement(body.len())
+            .unwrap_or(body.len()),
+    );
+    let mut read = 0;
+    loop {
+        let (result, n) =
+            decoder.decode_to_string_without_replacement(&body[read..], &mut text, !truncated);
+        read += n;
+        match result {
+            encoding_rs::DecoderResult::InputEmpty => return Ok(()),
+            encoding_rs::DecoderResult::Malformed(len, consumed_after) => {
+                let at = read - consumed_after as usize - len as usize;
+                return Err(format!(
+                    "body not valid {} (invalid byte sequence at offset {})",
+                    encoding.name(),
+                    at
+                ));
+            }
+            // Only reached if the size estimate overflowed; decode the rest chunk by chunk
+            encoding_rs::DecoderResult::OutputFull => text.reserve(body.len() - read + 16),
+        }
+    }
+}
+
+/// The first soft-error marker in the (buffered) body, if `detect_soft_errors` is on.
+fn soft_error_marker<'a>(ep: &'a EndpointConfig, body: &[u8]) -> Option<&'a str> {
+    if !ep.detect_soft_errors {
+        return None;
+    }
+    let text = String::from_utf8_lossy(body).to_lowercase();
+    let found = |marker: &&str| text.contains(&marker.to_lowercase());
+    match &ep.soft_error_markers {
+        Some(markers) => markers.iter().map(String::as_str).find(found),
+        None => DEFAULT_SOFT_ERROR_MARKERS.iter().copied().find(found),
+    }
+}
+
+/// Drains the response body, buffering at most `limit` bytes and timing the first byte.
+async fn read_body(
+    resp: &mut reqwest::Response,
+    limit: usize,
+    start: Instant,
+) -> reqwest::Result<ResponseBody> {
+    let mut body = ResponseBody {
+        bytes: Vec::new(),
+        size: 0,
+        ttfb: None,
+        total: Duration::ZERO,
+    };
+    while let Some(chunk) = resp.chunk().await? {
+        if chunk.is_empty() {
+            continue;
+        }
+        body.ttfb.get_or_insert_with(|| start.elapsed());
+        body.size += chunk.len();
+        let room = limit.saturating_sub(body.bytes.len());
+        body.bytes
+            .extend_from_slice(&chunk[..chunk.len().min(room)]);
+    }
+    body.total = start.elapsed();
+    // A 204 has no content by definition, so the client discards any the server sends; the
+    // declared length still shows the server meant to send some
+    if resp.status() == StatusCode::NO_CONTENT
+        && let Some(declared) = resp
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok()?.parse::<usize>().ok())
+    {
+        body.size = body.size.max(declared);
+    }
+    Ok(body)
+}
+
+pub async fn check_with_retries(
+    client: &Client,
+    ep: &EndpointConfig,
+    retries: u32,
+    default_timeout_ms: u64,
+    base_backoff_ms: u64,
+    max_backoff_ms: u64,
+) -> CheckOutcome {
+    let failover = ep.failover_urls.as_deref().unwrap_or_default();
+    // Attempt n targets the primary URL followed by each failover URL in turn
+    let target_for = |attempt: u32| -> &str {
+        match attempt as usize % (failover.len() + 1) {
+            0 => &ep.url,
+            i => &failover[i - 1],
+        }
+    };
+    let base_backoff_ms = ep.base_backoff_ms.unwrap_or(base_backoff_ms);
+    let max_backoff_ms = ep.max_backoff_ms.unwrap_or(max_backoff_ms);
+    let mut attempt: u32 = 0;
+    let mut last_outcome = check_target_once(client, ep, target_for(0), default_timeout_ms).await;
+    last_outcome.attempts = 1;
+    while attempt < retries {
+        match last_outcome.status {
+            HealthStatus::Up => break,
+            HealthStatus::Degraded(_) | HealthStatus::Down(_) => {
+                attempt += 1;
+                warn!(
+                    endpoint = ep.url.as_str(),
+                    attempt = attempt,
+                    "retrying failed endpoint"
+                );
+                // The server's Retry-After, else backoff with jitter
+                let delay = match last_outcome.retry_after_ms {
+                    Some(wait) => wait.min(max_backoff_ms),
+                    None => {
+                        let delay = retry_backoff_ms(base_backoff_ms, max_backoff_ms, attempt);
+                        let jitter = rand::random::<u64>() % (delay / 2).saturating_add(1);
+                        delay.saturating_add(jitter)
+                    }
+                };
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                // An escalated timeout is applied as the attempt's `timeout_ms`
+                let escalated;
+                let attempt_ep = if ep.retry_timeout_multiplier.is_some() {
+                    escalated = EndpointConfig {
+                        timeout_ms: Some(retry_timeout_ms(ep, default_timeout_ms, attempt)),
+                        ..ep.clone()
+                    };
+                    &escalated
+                } else {
+                    ep
+                };
+                let outcome =
+                    check_target_once(client, attempt_ep, target_for(attempt), default_timeout_ms)
+                        .await;
+                last_outcome = outcome;
+                last_outcome.attempts = attempt + 1;
+                if matches!(last_outcome.status, HealthStatus::Up) {
+                    break;
+                }
+            }
+        }
+    }
+    if !failover.is_empty() {
+        last_outcome.served_by = Some(redact_url(target_for(last_outcome.attempts - 1)));
+    }
+    if let (HealthStatus::Down(reason), Some(probe)) = (&last_outcome.status, &ep.on_failure_probe)
+    {
+        let timeout = Duration::from_millis(ep.timeout_ms.unwrap_or(default_timeout_ms));
+        let diagnosis = run_failure_probe(&ep.url, probe, timeout).await;
+        last_outcome.status = HealthStatus::Down(DownReason::Diagnosed {
+            reason: Box::new(reason.clone()),
+            diagnosis,
+        });
+    }
+    record_attempt_metrics(&last_outcome);
+    last_outcome
+}
+
+/// Delay before retry `attempt` (1 for the first retry), without jitter: `base_ms × 2^attempt`,
+/// capped at `max_ms`.
+pub fn retry_backoff_ms(base_ms: u64, max_ms: u64, attempt: u32) -> u64 {
+    let factor = 2u64.saturating_pow(attempt.min(20));
+    base_ms.saturating_mul(factor).min(max_ms)
+}
+
+/// Default cap on escalated retry timeouts (`retry_timeout_max_ms`)
+const DEFAULT_RETRY_TIMEOUT_MAX_MS: u64 = 60_000;
+
+/// Timeout of retry `attempt` (0 for the first try) with `retry_timeout_multiplier`:
+/// the endpoint's timeout × multiplier^attempt, capped at `retry_timeout_max_ms` but never
+/// below the first attempt's timeout.
+pub fn retry_timeout_ms(ep: &EndpointConfig, default_timeout_ms: u64, attempt: u32) -> u64 {
+    let base = ep.timeout_ms.unwrap_or(default_timeout_ms);
+    let Some(multiplier) = ep.retry_timeout_multiplier else {
+        return base;
+    };
+    let cap = ep
+        .retry_timeout_max_ms
+        .unwrap_or(DEFAULT_RETRY_TIMEOUT_MAX_MS)
+        .max(base);
+    let escalated = base as f64 * multiplier.powi(attempt.min(64) as i32);
+    (escalated.round() as u64).min(cap)
+}
+
+/// Records how many attempts a check took and whether a retry turned it around.
+fn record_attempt_metrics(outcome: &CheckOutcome) {
+    let endpoint = outcome.endpoint.clone();
+    histogram!("healthcheck_attempts", "endpoint" => endpoint.clone())
+        .record(outcome.attempts as f64);
+    if outcome.attempts > 1 && matches!(outcome.status, HealthStatus::Up) {
+        counter!("healthcheck_succeeded_on_retry_total", "endpoint" => endpoint).increment(1);
+    }
+}
+
+/// Runs the diagnostic probe against the endpoint's host and describes the likely failure layer.
+pub async fn run_failure_probe(url: &str, probe: &FailureProbe, timeout: Duration) -> String {
+    let parsed = match Url::parse(url) {
+        Ok(u) => u,
+        Err(e) => return format!("diagnostic probe skipped: invalid url ({})", e),
+    };
+    let host = match parsed.host_str() {
+        Some(h) => h.trim_start_matches('[').trim_end_matches(']').to_string(),
+        None => return "diagnostic probe skipped: url has no host".to_string(),
+    };
+    match probe {
+        FailureProbe::Tcp { port } => {
+            let port = match port.or_else(|| parsed.port_or_known_default()) {
+                Some(p) => p,
+                None => return "diagnostic probe skipped: unknown port".to_string(),
+            };
+            let connect = tokio::net::TcpStream::connect((host.as_str(), port));
+            match tokio::time::timeout(timeout, connect).await {
+                Ok(Ok(_)) => format!(
+                    "TCP connect to {}:{} succeeded → likely app-layer issue",
+                    host, port
+                ),
+                Ok(Err(e)) => format!(
+                    "TCP connect to {}:{} failed ({}) → likely network or host issue",
+                    host, port, e
+                ),
+                Err(_) => format!(
+                    "TCP connect to {}:{} timed out → likely network or host issue",
+                    host, port
+                ),
+            }
+        }
+        FailureProbe::Dns => {
+            let lookup = tokio::net::lookup_host((host.as_str(), 0));
+            match tokio::time::timeout(timeout, lookup).await {
+                Ok(Ok(addrs)) => {
+                    let count = addrs.count();
+                    format!(
+                        "DNS lookup for {} returned {} address(es) → likely network or app-layer issue",
+                        host, count
+                    )
+                }
+                Ok(Err(e)) => format!("DNS lookup for {} failed ({}) → likely DNS issue", host, e),
+                Err(_) => format!("DNS lookup for {} timed out → likely DNS issue", host),
+            }
+        }
+    }
+}
+
+/// Runs one sweep over the configured endpoints.
+///
+/// Runs on the caller's tokio runtime, of either flavor: it never starts a runtime or blocks
+/// a worker in place. Only the files the config references (`body_file`, schemas, golden
+/// files, CA bundles) are read synchronously, once, before the first check.
+pub async fn run_healthchecks(cfg: &Config) -> Result<Summary> {
+    let (summary, _) = run_healthchecks_detailed(cfg).await?;
+    Ok(summary)
+}
+
+/// Like [`run_healthchecks`], but also hands every outcome and the summary to `sinks` once
+/// the sweep is over (see [`emit_results`]).
+pub async fn run_healthchecks_with_sinks(
+    cfg: &Config,
+    sinks: &mut [Box<dyn ResultSink>],
+) -> Result<Summary> {
+    let (summary, outcomes) = run_healthchecks_detailed(cfg).await?;
+    emit_results(sinks, &summary, &outcomes)?;
+    Ok(summary)
+}
+
+/// Like [`run_healthchecks`], but also returns the individual outcomes.
+///
+/// Checks run concurrently, so outcomes are in completion order rather than config order;
+/// sort by `endpoint` for a stable order.
+pub async fn run_healthchecks_detailed(cfg: &Config) -> Result<(Summary, Vec<CheckOutcome>)> {
+    let body_budget = cfg.max_inflight_body_bytes.map(BodyBudget::new);
+    let mut endpoints = prepare_endpoints(cfg)?;
+    attach_body_budget(&mut endpoints, &body_budget);
+    #[cfg(feature = "ssh")]
+    let _tunnel = match &cfg.ssh_jump {
+        Some(jump) => Some(SshTunnel::open(jump).await?),
+        None => None,
+    };
+    #[cfg(feature = "k8s")]
+    let mut discovered = match &cfg.k8s_discovery {
+        Some(k) => K8sDiscoverer::new(k)?.discover().await?,
+        None => Vec::new(),
+    };
+    #[cfg(not(feature = "k8s"))]
+    let mut discovered: Vec<EndpointConfig> = Vec::new();
+    attach_body_budget(&mut discovered, &body_budget);
+    let mut refs = merge_discovered(&endpoints, &discovered);
+    order_sweep(cfg.order, &mut refs, &mut order_rng(cfg));
+    let mismatch = check_endpoint_count(cfg, refs.len())?;
+    if refs.is_empty() {
+        warn!("no endpoints configured");
+        let summary = Summary {
+            endpoint_count_mismatch: mismatch,
+            ..Default::default()
+        };
+        return Ok((summary, Vec::new()));
+    }
+    let clients = Clients::build(cfg, &endpoints)?;
+    let events = cfg
+        .event_sink
+        .as_ref()
+        .map(EventPublisher::spawn)
+        .transpose()?;
+    let (mut summary, outcomes) = run_endpoints(
+        cfg,
+        &clients,
+        events.as_ref(),
+        &refs,
+        cfg.concurrency,
+        false,
+    )
+    .await;
+    summary.endpoint_count_mismatch = mismatch;
+    if let Some(events) = events {
+        events.close().await;
+    }
+    Ok((summary, outcomes))
+}
+
+/// The latency histogram's name and configured `latency_buckets`, if any.
+///
+/// The crate only records through the `metrics` facade, so bucket boundaries belong to the
+/// recorder the embedding application installs. With `metrics-exporter-prometheus`:
+///
+/// ```ignore
+/// let mut builder = PrometheusBuilder::new();
+/// if let Some((name, buckets)) = latency_histogram_buckets(&cfg) {
+///     builder = builder.set_buckets_for_metric(Matcher::Full(name.to_string()), buckets)?;
+/// }
+/// ```
+pub fn latency_histogram_buckets(cfg: &Config) -> Option<(&'static str, &[f64])> {
+    cfg.latency_buckets
+        .as_deref()
+        .map(|buckets| ("healthcheck_latency_ms", buckets))
+}
+
+/// Applies `timeout_multiplier` to a configured timeout.
+fn scaled_timeout_ms(cfg: &Config, timeout_ms: u64) -> u64 {
+    match cfg.timeout_multiplier {
+        Some(multiplier) => (timeout_ms as f64 * multiplier).round().max(1.0) as u64,
+        None => timeout_ms,
+    }
+}
+
+/// Compares the resolved endpoint count with `expected_endpoint_count`.
+///
+/// Guards against config generators silently dropping endpoints: a mismatch is a warning, or
+/// an error with `strict_endpoint_count`.
+fn check_endpoint_count(cfg: &Config, actual: usize) -> Result<Option<EndpointCountMismatch>> {
+    match cfg.expected_endpoint_count {
+        Some(expected) if expected != actual => {
+            if cfg.strict_endpoint_count {
+                anyhow::bail!(
+                    "resolved {} endpoints, expected_endpoint_count is {}",
+                    actual,
+                    expected
+                );
+            }
+            warn!(
+                expected,
+                actual, "resolved endpoint count differs from expected_endpoint_count"
+            );
+            Ok(Some(EndpointCountMismatch { expected, actual }))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Runs a one-shot sweep, re-running it up to `sweep_retries` times while it is unhealthy.
+///
+/// Only the last sweep's result is returned. Unlike per-endpoint retries, every endpoint is
+/// checked again, which gives a deployment time to settle before a gate fails.
+pub async fn run_sweeps(cfg: &Config) -> Result<(Summary, Vec<CheckOutcome>)> {
+    let attempts = cfg.sweep_retries.saturating_add(1);
+    let mut sweep = 1;
+    loop {
+        let (summary, outcomes) = run_healthchecks_detailed(cfg).await?;
+        if summary.is_healthy(cfg) || sweep >= attempts {
+            if sweep > 1 {
+                info!(
+                    sweep,
+                    healthy = summary.is_healthy(cfg),
+                    "final sweep after retries"
+                );
+            }
+            return Ok((summary, outcomes));
+        }
+        warn!(
+            sweep,
+            of = attempts,
+            up = summary.up,
+            degraded = summary.degraded,
+            down = summary.down,
+            retry_in_sec = cfg.sweep_retry_delay_sec,
+            "sweep unhealthy; retrying"
+        );
+        tokio::time::sleep(Duration::from_secs(cfg.sweep_retry_delay_sec)).await;
+        sweep += 1;
+    }
+}
+
+/// Re-runs [`run_healthchecks`] every `interval` until no endpoint is down, or fails once
+/// `timeout` has elapsed.
+///
+/// Meant as a post-deploy gate; unlike [`run_watch`] there are no circuit breakers. Degraded
+/// endpoints count as not ready unless `tolerate_degraded` is set.
+pub async fn wait_until_healthy(
+    cfg: &Config,
+    timeout: Duration,
+    interval: Duration,
+) -> Result<Summary> {
+    let deadline = Instant::now() + timeout;
+    let mut attempt = 0u32;
+    loop {
+        attempt += 1;
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let Ok(summary) = tokio::time::timeout(remaining, run_healthchecks(cfg)).await else {
+            anyhow::bail!(
+                "endpoints not healthy after {}s (last sweep still running)",
+                timeout.as_secs()
+            );
+        };
+        let summary = summary?;
+        let ready = summary.total > 0
+            && summary.down == 0
+            && (summary.degraded == 0 || cfg.tolerate_degraded);
+        if ready {
+            info!(attempt, total = summary.total, "all endpoints healthy");
+            return Ok(summary);
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            anyhow::bail!(
+                "endpoints not healthy after {}s ({} of {} up)",
+                timeout.as_secs(),
+                summary.up,
+                summary.total
+            );
+        }
+        info!(
+            attempt,
+            up = summary.up,
+            total = summary.total,
+            remaining_sec = remaining.as_secs(),
+            "waiting for endpoints to become healthy"
+        );
+        tokio::time::sleep(interval.min(remaining)).await;
+    }
+}
+
+/// Shares one `max_inflight_body_bytes` budget between all endpoints of a run.
+fn attach_body_budget(endpoints: &mut [EndpointConfig], budget: &Option<BodyBudget>) {
+    for ep in endpoints {
+        ep.body_budget = budget.clone();
+    }
+}
+
+/// Static endpoints followed by discovered ones whose URL is not configured already.
+fn merge_discovered<'a>(
+    endpoints: &'a [EndpointConfig],
+    discovered: &'a [EndpointConfig],
+) -> Vec<&'a EndpointConfig> {
+    let mut merged: Vec<&EndpointConfig> = endpoints.iter().collect();
+    for ep in discovered {
+        if !endpoints.iter().any(|e| e.url == ep.url) {
+            merged.push(ep);
+        }
+    }
+    merged
+}
+
+/// Seeded from `order_seed` if set.
+fn order_rng(cfg: &Config) -> StdRng {
+    match cfg.order_seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    }
+}
+
+/// Puts a sweep's endpoints in the configured start order.
+fn order_sweep(order: CheckOrder, endpoints: &mut [&EndpointConfig], rng: &mut StdRng) {
+    match order {
+        CheckOrder::Config => {}
+        CheckOrder::Random => endpoints.shuffle(rng),
+        CheckOrder::Priority => endpoints.sort_by_key(|ep| std::cmp::Reverse(ep.priority)),
+    }
+}
+
+/// Checks the given endpoints with the shared clients.
+///
+/// Endpoints are borrowed through the whole stream pipeline so large inventories are
+/// materialized only once, by [`prepare_endpoints`]. A `warmup` sweep records no latency
+/// histograms and does not evaluate the latency objective.
+async fn run_endpoints(
+    cfg: &Config,
+    clients: &Clients,
+    events: Option<&EventPublisher>,
+    endpoints: &[&EndpointConfig],
+    concurrency: usize,
+    warmup: bool,
+) -> (Summary, Vec<CheckOutcome>) {
     if endpoints.is_empty() {
         warn!("no endpoints configured");
-        return Ok(Summary::default());
+        return (Summary::default(), Vec::new());
     }
-    let client = build_client(cfg)?;
-    let semaphore = Arc::new(Semaphore::new(cfg.concurrency));
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let host_semaphores = cfg
+        .max_concurrency_per_host
+        .map(|limit| host_semaphores(endpoints, limit))
+        .unwrap_or_default();
 
     info!(
         total = endpoints.len(),
-        concurrency = cfg.concurrency,
-        timeout_ms = cfg.request_timeout_ms,
+        concurrency = concurrency,
+        timeout_ms = scaled_timeout_ms(cfg, cfg.request_timeout_ms),
         retries = cfg.retries,
         "starting healthchecks"
     );
 
-    let outcomes = stream::iter(endpoints)
+    let progress = SweepProgress::new(endpoints.len());
+    // Collect the (lazy) futures first: a borrowing closure inside the stream type makes
+    // the caller's future fail `Send` checks when spawned.
+    let checks: Vec<_> = endpoints
+        .iter()
         .map(|endpoint| {
-            let client = client.clone();
-            let sem = Arc::clone(&semaphore);
-            let retries = endpoint.retries.unwrap_or(cfg.retries);
-            let default_timeout_ms = cfg.request_timeout_ms;
-            let base_backoff_ms = cfg.base_backoff_ms;
-            let max_backoff_ms = cfg.max_backoff_ms;
-            async move {
-                let _permit = sem.acquire_owned().await.expect("semaphore closed");
-                debug!(endpoint = %endpoint.url, "checking endpoint");
-                let outcome = check_with_retries(&client, &endpoint, retries, default_timeout_ms, base_backoff_ms, max_backoff_ms).await;
-                match &outcome.status {
-                    HealthStatus::Up => {
-                        info!(endpoint = %outcome.endpoint, latency_ms = ?outcome.latency_ms, attempts = outcome.attempts, "endpoint up");
-                    }
-                    HealthStatus::Down(reason) => {
-                        error!(endpoint = %outcome.endpoint, attempts = outcome.attempts, reason = %reason, "endpoint down");
-                    }
-                }
-                outcome
-            }
+            check_and_log(
+                cfg,
+                clients,
+                events,
+                endpoint,
+                Arc::clone(&semaphore),
+                host_authority(&endpoint.url).and_then(|host| host_semaphores.get(&host).cloned()),
+                &progress,
+            )
         })
-        .buffer_unordered(cfg.concurrency)
-        .collect::<Vec<_>>()
-        .await;
+        .collect();
+    // With per-host limits, checks waiting on a busy host must not hold the stream's slots
+    // (the global semaphore still bounds the checks actually running).
+    let polled = if host_semaphores.is_empty() {
+        concurrency
+    } else {
+        checks.len()
+    };
+    let sweep = stream::iter(checks)
+        .buffer_unordered(polled)
+        .collect::<Vec<_>>();
+    let log_every = (cfg.progress_log_interval_sec > 0)
+        .then(|| Duration::from_secs(cfg.progress_log_interval_sec));
+    let mut outcomes = if warmup {
+        WARMUP
+            .scope((), progress.report_while(log_every, sweep))
+            .await
+    } else {
+        progress.report_while(log_every, sweep).await
+    };
+
+    if cfg.highlight_outliers {
+        for outcome in mark_latency_outliers(&mut outcomes, cfg.outlier_mad_k) {
+            warn!(endpoint = %outcome.endpoint, latency_ms = ?outcome.latency_ms, "latency outlier");
+        }
+    }
 
     let mut summary = Summary {
         total: outcomes.len(),
-        up: 0,
-        down: 0,
+        ..Default::default()
     };
-    for outcome in outcomes {
+    for outcome in &outcomes {
         match outcome.status {
             HealthStatus::Up => summary.up += 1,
-            HealthStatus::Down(_) => summary.down += 1,
+            HealthStatus::Degraded(_) => summary.degraded += 1,
+            HealthStatus::Down(_) => {
+                summary.down += 1;
+                if outcome.reason_code == Some(ReasonCode::Timeout) {
+                    summary.timed_out += 1;
+                }
+            }
+        }
+    }
+    summary.composite_score = composite_score(&outcomes);
+    if let Some(score) = summary.composite_score {
+        gauge!("healthcheck_composite_score").set(score);
+    }
+    if let Some(objective_ms) = cfg.latency_objective_ms
+        && !warmup
+        && let Some(ratio) = latency_objective_ratio(&outcomes, objective_ms)
+    {
+        let met = ratio >= cfg.latency_objective_ratio;
+        gauge!("healthcheck_latency_objective_met").set(if met { 1.0 } else { 0.0 });
+        if !met {
+            warn!(
+                objective_ms,
+                ratio,
+                required = cfg.latency_objective_ratio,
+                "latency objective missed"
+            );
         }
+        summary.latency_objective_met = Some(met);
     }
     info!(
         total = summary.total,
         up = summary.up,
+        degraded = summary.degraded,
         down = summary.down,
+        timed_out = summary.timed_out,
         "healthcheck summary"
     );
-    Ok(summary)
+    (summary, outcomes)
+}
+
+/// One semaphore of `limit` permits per host among `endpoints`.
+fn host_semaphores(
+    endpoints: &[&EndpointConfig],
+    limit: usize,
+) -> std::collections::HashMap<String, Arc<Semaphore>> {
+    endpoints
+        .iter()
+        .filter_map(|ep| host_authority(&ep.url))
+        .map(|host| (host, Arc::new(Semaphore::new(limit))))
+        .collect()
+}
+
+/// `host:port` of `url`, with the scheme's default port if none is given.
+fn host_authority(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    Some(format!(
+        "{}:{}",
+        url.host_str()?,
+        url.port_or_known_default()?
+    ))
+}
+
+async fn check_and_log(
+    cfg: &Config,
+    clients: &Clients,
+    events: Option<&EventPublisher>,
+    endpoint: &EndpointConfig,
+    sem: Arc<Semaphore>,
+    host_sem: Option<Arc<Semaphore>>,
+    progress: &SweepProgress,
+) -> CheckOutcome {
+    // The host permit first, so a check queued behind its host does not hold a global one
+    let _host_permit = match host_sem {
+        Some(sem) => Some(sem.acquire_owned().await.expect("semaphore closed")),
+        None => None,
+    };
+    let _permit = sem.acquire_owned().await.expect("semaphore closed");
+    let _in_flight = progress.start();
+    debug!(endpoint = %endpoint.url, "checking endpoint");
+    let retries = endpoint.retries.unwrap_or(cfg.retries);
+    let mut outcome = check_with_retries(
+        clients.for_endpoint(endpoint),
+        endpoint,
+        retries,
+        scaled_timeout_ms(cfg, cfg.request_timeout_ms),
+        cfg.base_backoff_ms,
+        cfg.max_backoff_ms,
+    )
+    .await;
+    if endpoint.kind == CheckKind::Http {
+        outcome.source_address = endpoint.local_address.or(cfg.local_address);
+    }
+    outcome.weight = endpoint.weight.unwrap_or(1.0);
+    match &outcome.status {
+        HealthStatus::Up => {
+            info!(endpoint = %outcome.endpoint, latency_ms = ?outcome.latency_ms, attempts = outcome.attempts, correlation_id = ?outcome.correlation_id, remote_addr = ?outcome.remote_addr, "endpoint up");
+        }
+        HealthStatus::Degraded(reason) => {
+            warn!(endpoint = %outcome.endpoint, attempts = outcome.attempts, reason = %reason, correlation_id = ?outcome.correlation_id, remote_addr = ?outcome.remote_addr, "endpoint degraded");
+        }
+        HealthStatus::Down(reason) => {
+            error!(endpoint = %outcome.endpoint, attempts = outcome.attempts, reason = %reason, correlation_id = ?outcome.correlation_id, remote_addr = ?outcome.remote_addr, "endpoint down");
+        }
+    }
+    if let Some(events) = events {
+        events.publish(&outcome);
+    }
+    outcome
+}
+
+/// Weighted health across outcomes, normalized to 0–100.
+///
+/// Each outcome contributes its weight times 1 (up), 0.5 (degraded) or 0 (down). `None` when
+/// there are no outcomes or all weights are zero.
+pub fn composite_score(outcomes: &[CheckOutcome]) -> Option<f64> {
+    let total: f64 = outcomes.iter().map(|o| o.weight).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let healthy: f64 = outcomes
+        .iter()
+        .map(|o| {
+            o.weight
+                * match o.status {
+                    HealthStatus::Up => 1.0,
+                    HealthStatus::Degraded(_) => 0.5,
+                    HealthStatus::Down(_) => 0.0,
+                }
+        })
+        .sum();
+    Some(healthy / total * 100.0)
+}
+
+/// Fraction of successful checks whose latency is within `objective_ms`.
+fn latency_objective_ratio(outcomes: &[CheckOutcome], objective_ms: u64) -> Option<f64> {
+    let latencies: Vec<u128> = outcomes.iter().filter_map(|o| o.latency_ms).collect();
+    if latencies.is_empty() {
+        return None;
+    }
+    let within = latencies
+        .iter()
+        .filter(|&&l| l <= objective_ms as u128)
+        .count();
+    Some(within as f64 / latencies.len() as f64)
+}
+
+/// Flags outcomes whose latency exceeds `median + k·MAD` of all measured latencies.
+///
+/// The MAD is scaled by 1.4826 to approximate a standard deviation and floored at 1ms so a
+/// perfectly uniform fleet does not flag sub-millisecond jitter. At least three latency
+/// samples are required. Returns the outcomes that were flagged.
+pub fn mark_latency_outliers(outcomes: &mut [CheckOutcome], k: f64) -> Vec<&CheckOutcome> {
+    let mut latencies: Vec<f64> = outcomes
+        .iter()
+        .filter_map(|o| o.latency_ms.map(|l| l as f64))
+        .collect();
+    if latencies.len() < 3 {
+        return Vec::new();
+    }
+    let med = median(&mut latencies);
+    let mut deviations: Vec<f64> = latencies.iter().map(|l| (l - med).abs()).collect();
+    let mad = (median(&mut deviations) * 1.4826).max(1.0);
+    let threshold = med + k * mad;
+    for outcome in outcomes.iter_mut() {
+        outcome.outlier = outcome.latency_ms.is_some_and(|l| l as f64 > threshold);
+    }
+    outcomes.iter().filter(|o| o.outlier).collect()
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
 }
 
 pub async fn run_watch(cfg: &Config) -> Result<()> {
+    if cfg.shutdown_drain {
+        run_watch_until(cfg, shutdown_signal()?).await
+    } else {
+        // Signals terminate the process mid-sweep, as they always have
+        run_watch_until(cfg, std::future::pending()).await
+    }
+}
+
+/// Resolves on SIGTERM or Ctrl-C (SIGINT).
+fn shutdown_signal() -> Result<impl std::future::Future<Output = ()>> {
+    // Registered now, so a signal during the first sweep is not missed
+    #[cfg(unix)]
+    let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        .context("failed to install SIGTERM handler")?;
+    Ok(async move {
+        #[cfg(unix)]
+        tokio::select! {
+            _ = terminate.recv() => info!("received SIGTERM"),
+            _ = tokio::signal::ctrl_c() => info!("received Ctrl-C"),
+        }
+        #[cfg(not(unix))]
+        let _ = tokio::signal::ctrl_c().await;
+    })
+}
+
+/// Runs the watch loop until `shutdown` completes.
+///
+/// With `shutdown_drain`, the loop then runs one last sweep over every endpoint (circuit
+/// breakers included), resolves open PagerDuty incidents and waits for pending alert and
+/// event deliveries, all within `shutdown_timeout_sec`; without it, it stops at the next
+/// wait between sweeps. [`run_watch`] uses SIGTERM and Ctrl-C when `shutdown_drain` is set.
+pub async fn run_watch_until(
+    cfg: &Config,
+    shutdown: impl std::future::Future<Output = ()>,
+) -> Result<()> {
     let interval_sec = match cfg.watch_interval_sec {
         Some(n) if n > 0 => n,
         _ => return Ok(()), // nothing to do
     };
     use std::collections::HashMap;
-    let mut breaker: HashMap<String, (u32, Option<Instant>)> = HashMap::new();
-    let mut last_summary: Summary;
+    let mut breaker: HashMap<String, Breaker> = HashMap::new();
+    let pagerduty = cfg
+        .pagerduty_routing_key
+        .as_deref()
+        .map(|key| PagerDuty::new(key, &cfg.pagerduty_events_url).map(Arc::new))
+        .transpose()?;
+    let notifier = cfg
+        .notify
+        .as_ref()
+        .map(|notify| WebhookNotifier::new(notify).map(Arc::new))
+        .transpose()?;
+    let mut states = StateTracker::new();
+    // Endpoints with a triggered PagerDuty incident
+    let mut incidents: std::collections::HashSet<String> = std::collections::HashSet::new();
+    let mut last_summary = Summary::default();
     let metrics_interval = cfg.metrics_log_interval_sec.unwrap_or(0);
     let mut metrics_ticker = if metrics_interval > 0 {
         Some(tokio::time::interval(Duration::from_secs(metrics_interval)))
@@ -386,77 +3708,473 @@ pub async fn run_watch(cfg: &Config) -> Result<()> {
     if let Some(ticker) = &mut metrics_ticker {
         ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
     }
+    let body_budget = cfg.max_inflight_body_bytes.map(BodyBudget::new);
+    let mut endpoints = prepare_endpoints(cfg)?;
+    attach_body_budget(&mut endpoints, &body_budget);
+    #[cfg(feature = "ssh")]
+    let _tunnel = match &cfg.ssh_jump {
+        Some(jump) => Some(SshTunnel::open(jump).await?),
+        None => None,
+    };
+    let clients = Clients::build(cfg, &endpoints)?;
+    let events = cfg
+        .event_sink
+        .as_ref()
+        .map(EventPublisher::spawn)
+        .transpose()?;
+    let mut self_health = SelfHealth::new(Duration::from_secs(
+        interval_sec.saturating_mul(cfg.self_health_stale_intervals.max(1) as u64),
+    ));
+    let mut check_requests = cfg
+        .self_health_addr
+        .is_some()
+        .then(|| self_health.enable_on_demand_checks());
+    // `POST /check` requests waiting for the next sweep
+    let mut on_demand: Vec<CheckReply> = Vec::new();
+    let self_health = Arc::new(self_health);
+    if let Some(addr) = &cfg.self_health_addr {
+        Arc::clone(&self_health).serve(addr).await?;
+    }
+    // Stops serving when the loop returns
+    #[cfg(feature = "prometheus")]
+    let _metrics_exporter = match &cfg.metrics_listen_addr {
+        Some(addr) => Some(MetricsExporter::start(cfg, addr).await?),
+        None => None,
+    };
+    let apdex_targets: HashMap<String, u64> = endpoints
+        .iter()
+        .filter_map(|ep| ep.apdex_target_ms.map(|t| (redact_url(&ep.url), t)))
+        .collect();
+    let mut apdex_windows: HashMap<String, RollingWindow<ApdexSample>> = HashMap::new();
+    let mut availability = cfg
+        .availability_window_sec
+        .map(|sec| AvailabilityTracker::new(Duration::from_secs(sec)));
+    #[cfg(feature = "k8s")]
+    let k8s = cfg
+        .k8s_discovery
+        .as_ref()
+        .map(K8sDiscoverer::new)
+        .transpose()?;
+    #[allow(unused_mut)]
+    let mut discovered: Vec<EndpointConfig> = Vec::new();
+    let mut adaptive = (cfg.concurrency_mode == ConcurrencyMode::Adaptive)
+        .then(|| AdaptiveConcurrency::new(cfg.concurrency, cfg.concurrency_max));
+    let mut first_sweep = true;
+    let mut warmup_left = cfg.metrics_warmup_iterations.unwrap_or(0);
+    let mut order_rng = order_rng(cfg);
+    let mut shutdown = std::pin::pin!(shutdown);
+    // Set once shutdown was requested; the drain must end by then
+    let mut drain_deadline: Option<Instant> = None;
+    // In-flight PagerDuty and notify webhook deliveries
+    let mut deliveries: Vec<tokio::task::JoinHandle<()>> = Vec::new();
     loop {
-        // Prepare a filtered config if breaker is open for endpoints
-        let mut cfg_clone = cfg.clone();
-        let base_eps: Vec<EndpointConfig> = if let Some(eps) = &cfg.endpoints {
-            eps.clone()
-        } else {
-            cfg.endpoints_to_check
-                .iter()
-                .map(|u| EndpointConfig {
-                    url: u.clone(),
-                    method: default_method(),
-                    timeout_ms: None,
-                    retries: None,
-                    expected_status: None,
-                    headers: None,
-                })
-                .collect()
-        };
+        let draining = drain_deadline.is_some();
+        if draining {
+            if !cfg.shutdown_drain {
+                if let Some(events) = events {
+                    events.close().await;
+                }
+                return Ok(());
+            }
+            info!(
+                timeout_sec = cfg.shutdown_timeout_sec,
+                "shutting down; running a final sweep"
+            );
+        }
+        #[cfg(feature = "k8s")]
+        if let Some(k8s) = &k8s {
+            match k8s.discover().await {
+                Ok(mut eps) => {
+                    attach_body_budget(&mut eps, &body_budget);
+                    discovered = eps;
+                }
+                Err(e) => warn!(error = %e, "k8s discovery failed; keeping previous targets"),
+            }
+        }
+        // Skip endpoints whose breaker is open; half-open ones get a single probe request
         let now = Instant::now();
-        let filtered: Vec<EndpointConfig> = base_eps
-            .into_iter()
-            .filter(|ep| {
-                if let Some((fails, until)) = breaker.get(&ep.url)
-                    && let Some(deadline) = until
-                    && *fails >= cfg.cb_failures_threshold
-                    && *deadline > now
+        for (url, state) in breaker.iter_mut() {
+            if let Breaker::Open { failures, until } = *state
+                && until <= now
+            {
+                info!(endpoint = %redact_url(url), "circuit half-open; probing once");
+                *state = Breaker::HalfOpen { failures };
+            }
+        }
+        let targets = merge_discovered(&endpoints, &discovered);
+        let mismatch = check_endpoint_count(cfg, targets.len())?;
+        let probes: Vec<Option<EndpointConfig>> = targets
+            .iter()
+            .map(|ep| {
+                matches!(breaker.get(&ep.url), Some(Breaker::HalfOpen { .. })).then(|| {
+                    EndpointConfig {
+                        retries: Some(0),
+                        connection_retries: Some(0),
+                        ..(*ep).clone()
+                    }
+                })
+            })
+            .collect();
+        let mut filtered: Vec<&EndpointConfig> = targets
+            .iter()
+            .zip(&probes)
+            .filter(|(ep, _)| {
+                if draining {
+                    return true;
+                }
+                if let Some(Breaker::Open { until, .. }) = breaker.get(&ep.url)
+                    && *until > now
                 {
                     warn!(endpoint = %ep.url, "circuit open; skipping this iteration");
                     return false;
                 }
                 true
             })
+            .map(|(ep, probe)| probe.as_ref().unwrap_or(ep))
             .collect();
-        cfg_clone.endpoints = Some(filtered);
-        let summary = run_healthchecks(&cfg_clone).await?;
+        if filtered.is_empty()
+            && !targets.is_empty()
+            && let Some(wait) = all_open_wait(&breaker, now)
+        {
+            // Nothing to check; an empty run would also reset the breakers below
+            let wait_sec = wait.as_secs_f64().ceil() as u64;
+            warn!(
+                next_attempt_sec = wait_sec,
+                "all endpoints in cooldown; next attempt in {}s", wait_sec
+            );
+            self_health.record_sweep();
+            for reply in on_demand.drain(..) {
+                let _ = reply.send((false, last_summary.to_json()));
+            }
+            match next_iteration_or_shutdown(
+                wait_for_next_iteration(
+                    &mut metrics_ticker,
+                    &last_summary,
+                    interval_sec,
+                    &mut check_requests,
+                ),
+                shutdown.as_mut(),
+            )
+            .await
+            {
+                Some(replies) => on_demand.extend(replies),
+                None => drain_deadline = Some(shutdown_deadline(cfg)),
+            }
+            continue;
+        }
+        order_sweep(cfg.order, &mut filtered, &mut order_rng);
+        let concurrency = adaptive
+            .as_ref()
+            .map_or(cfg.concurrency, AdaptiveConcurrency::current);
+        let warmup = warmup_left > 0;
+        if warmup {
+            info!(remaining = warmup_left, "metrics warmup iteration");
+            warmup_left -= 1;
+        }
+        let sweep = run_endpoints(
+            cfg,
+            &clients,
+            events.as_ref(),
+            &filtered,
+            concurrency,
+            warmup,
+        );
+        let (mut summary, outcomes) = match drain_deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline.into(), sweep).await {
+                Ok(result) => result,
+                Err(_) => {
+                    warn!(
+                        timeout_sec = cfg.shutdown_timeout_sec,
+                        "final sweep did not finish in time; exiting without it"
+                    );
+                    drain(pagerduty.as_ref(), &incidents, deliveries, events, deadline).await;
+                    return Ok(());
+                }
+            },
+            None => sweep.await,
+        };
+        summary.endpoint_count_mismatch = mismatch;
+        self_health.record_sweep();
+
+        if let Some(adaptive) = &mut adaptive
+            && !warmup
+        {
+            let mut latencies: Vec<f64> = outcomes
+                .iter()
+                .filter_map(|o| o.latency_ms.map(|l| l as f64))
+                .collect();
+            if let Some(p95) = percentile(&mut latencies, 95.0) {
+                let next = adaptive.observe(p95);
+                gauge!("healthcheck_concurrency").set(next as f64);
+                debug!(p95_ms = p95, concurrency = next, "adjusted concurrency");
+            }
+        }
+
+        let now = Instant::now();
+        if !warmup {
+            for outcome in &outcomes {
+                if let Some(&target) = apdex_targets.get(&outcome.endpoint) {
+                    apdex_windows
+                        .entry(outcome.endpoint.clone())
+                        .or_insert_with(|| {
+                            RollingWindow::new(Duration::from_secs(cfg.apdex_window_sec))
+                        })
+                        .push(now, ApdexSample::from_outcome(outcome, target));
+                }
+            }
+        }
+        let mut apdex = serde_json::Map::new();
+        for (endpoint, window) in &mut apdex_windows {
+            window.prune(now);
+            if let Some(score) = apdex_score(window.iter()) {
+                gauge!("healthcheck_apdex", "endpoint" => endpoint.clone()).set(score);
+                apdex.insert(endpoint.clone(), score.into());
+            }
+        }
+
+        if let Some(tracker) = &mut availability {
+            tracker.record(now, &outcomes);
+            summary.availability = tracker.ratios(now);
+            for (endpoint, ratio) in &summary.availability {
+                gauge!("healthcheck_availability_ratio", "endpoint" => endpoint.clone())
+                    .set(*ratio);
+            }
+        }
+
+        if let Some(notifier) = &notifier {
+            for change in states.observe(&outcomes) {
+                info!(
+                    endpoint = %change.endpoint,
+                    from = change.old_state,
+                    to = change.new_state,
+                    "endpoint changed state"
+                );
+                deliveries.retain(|d| !d.is_finished());
+                deliveries.push(notifier.dispatch(change));
+            }
+        }
+
+        let mut json = summary.to_json();
+        if !apdex.is_empty() {
+            json["apdex"] = serde_json::Value::Object(apdex);
+        }
         if cfg.summary_json {
-            let json = serde_json::to_string(&serde_json::json!({
-                "total": summary.total,
-                "up": summary.up,
-                "down": summary.down
-            }))?;
-            println!("{}", json);
+            println!("{}", serde_json::to_string(&json)?);
+        }
+        for reply in on_demand.drain(..) {
+            let _ = reply.send((summary.is_healthy(cfg), json.clone()));
         }
         last_summary = summary.clone();
+        if std::mem::take(&mut first_sweep)
+            && cfg.watch_require_initial_healthy
+            && summary.has_failures(cfg)
+        {
+            if let Some(events) = events {
+                events.close().await;
+            }
+            anyhow::bail!(
+                "initial sweep unhealthy ({} down, {} degraded); not starting to watch",
+                summary.down,
+                summary.degraded
+            );
+        }
 
         // Update breaker state based on last run
-        if let Some(eps) = &cfg.endpoints {
-            for ep in eps {
-                if last_summary.down > 0 {
-                    // rough heuristic: if any down, increment count for those known failing endpoints
-                    let entry = breaker.entry(ep.url.clone()).or_insert((0, None));
-                    entry.0 = entry.0.saturating_add(1);
-                    if entry.0 >= cfg.cb_failures_threshold {
-                        entry.1 = Some(Instant::now() + Duration::from_secs(cfg.cb_cooldown_sec));
+        for ep in &targets {
+            let endpoint = redact_url(&ep.url);
+            let mut own = outcomes
+                .iter()
+                .filter(|o| o.endpoint == endpoint)
+                .peekable();
+            if own.peek().is_none() {
+                // Not checked this sweep (open breaker): keep its state
+                continue;
+            }
+            let failure = own.find_map(|o| o.status.reason());
+            if let Some(reason) = failure {
+                let state = breaker
+                    .entry(ep.url.clone())
+                    .or_insert(Breaker::Closed { failures: 0 });
+                let probing = matches!(state, Breaker::HalfOpen { .. });
+                let failures = state.failures().saturating_add(1);
+                *state = Breaker::Closed { failures };
+                // A failed half-open probe re-opens the breaker whatever the threshold
+                if probing || failures >= cfg.cb_failures_threshold {
+                    let until = Instant::now() + Duration::from_secs(cfg.cb_cooldown_sec);
+                    *state = Breaker::Open { failures, until };
+                    if probing {
+                        warn!(endpoint = %endpoint, "probe failed; circuit re-opened");
                     }
-                } else {
-                    breaker.remove(&ep.url);
+                    if let Some(pd) = &pagerduty
+                        && incidents.insert(ep.url.clone())
+                    {
+                        let summary = format!(
+                            "{}: circuit breaker open after {} failed runs ({})",
+                            endpoint, failures, reason
+                        );
+                        deliveries.retain(|d| !d.is_finished());
+                        deliveries
+                            .push(pd.dispatch(endpoint, pagerduty::Incident::Trigger { summary }));
+                    }
+                }
+            } else {
+                if let Some(Breaker::HalfOpen { .. }) = breaker.remove(&ep.url) {
+                    info!(endpoint = %endpoint, "probe succeeded; circuit closed");
+                }
+                if let Some(pd) = &pagerduty
+                    && incidents.remove(&ep.url)
+                {
+                    deliveries.retain(|d| !d.is_finished());
+                    deliveries.push(pd.dispatch(endpoint, pagerduty::Incident::Resolve));
                 }
             }
         }
 
-        // Periodic metrics logging
-        if let Some(ticker) = &mut metrics_ticker {
-            tokio::select! {
-                _ = ticker.tick() => {
-                    info!(total = last_summary.total, up = last_summary.up, down = last_summary.down, "periodic summary");
+        if let Some(deadline) = drain_deadline {
+            info!("final sweep done; exiting");
+            drain(pagerduty.as_ref(), &incidents, deliveries, events, deadline).await;
+            return Ok(());
+        }
+        match next_iteration_or_shutdown(
+            wait_for_next_iteration(
+                &mut metrics_ticker,
+                &last_summary,
+                interval_sec,
+                &mut check_requests,
+            ),
+            shutdown.as_mut(),
+        )
+        .await
+        {
+            Some(replies) => on_demand.extend(replies),
+            None => drain_deadline = Some(shutdown_deadline(cfg)),
+        }
+    }
+}
+
+fn shutdown_deadline(cfg: &Config) -> Instant {
+    Instant::now() + Duration::from_secs(cfg.shutdown_timeout_sec)
+}
+
+/// Waits for the next watch iteration, or `None` once `shutdown` completes.
+async fn next_iteration_or_shutdown(
+    wait: impl std::future::Future<Output = Vec<CheckReply>>,
+    shutdown: std::pin::Pin<&mut impl std::future::Future<Output = ()>>,
+) -> Option<Vec<CheckReply>> {
+    tokio::select! {
+        replies = wait => Some(replies),
+        () = shutdown => None,
+    }
+}
+
+/// Shutdown drain after the final sweep: resolves the open PagerDuty `incidents`, since no
+/// later run would, and waits for alert and notify deliveries until `deadline` and for buffered events.
+async fn drain(
+    pagerduty: Option<&Arc<PagerDuty>>,
+    incidents: &std::collections::HashSet<String>,
+    mut deliveries: Vec<tokio::task::JoinHandle<()>>,
+    events: Option<EventPublisher>,
+    deadline: Instant,
+) {
+    if let Some(pd) = pagerduty {
+        for url in incidents {
+            deliveries.push(pd.dispatch(redact_url(url), pagerduty::Incident::Resolve));
+        }
+    }
+    let pending = futures::future::join_all(deliveries);
+    if tokio::time::timeout_at(deadline.into(), pending)
+        .await
+        .is_err()
+    {
+        warn!("alert deliveries still pending at the shutdown deadline; dropping them");
+    }
+    if let Some(events) = events {
+        events.close().await;
+    }
+}
+
+/// Circuit breaker state of one endpoint in watch mode.
+#[derive(Debug, Clone, Copy)]
+enum Breaker {
+    /// Checked every sweep, after `failures` consecutive failed runs
+    Closed { failures: u32 },
+    /// Skipped until the cooldown ends
+    Open { failures: u32, until: Instant },
+    /// Cooldown over: one probe request, without retries, closes or re-opens the breaker
+    HalfOpen { failures: u32 },
+}
+
+impl Breaker {
+    fn failures(&self) -> u32 {
+        match *self {
+            Breaker::Closed { failures }
+            | Breaker::Open { failures, .. }
+            | Breaker::HalfOpen { failures } => failures,
+        }
+    }
+}
+
+/// Time until the first open breaker turns half-open, if any breaker is open.
+///
+/// Only called once every target has been filtered out, so an open breaker means all are.
+fn all_open_wait(
+    breaker: &std::collections::HashMap<String, Breaker>,
+    now: Instant,
+) -> Option<Duration> {
+    breaker
+        .values()
+        .filter_map(|state| match *state {
+            Breaker::Open { until, .. } if until > now => Some(until - now),
+            _ => None,
+        })
+        .min()
+}
+
+/// Waits for the next watch iteration, returning early with the pending `POST /check`
+/// requests once one arrives.
+async fn wait_for_next_iteration(
+    metrics_ticker: &mut Option<tokio::time::Interval>,
+    last_summary: &Summary,
+    interval_sec: u64,
+    check_requests: &mut Option<tokio::sync::mpsc::Receiver<CheckReply>>,
+) -> Vec<CheckReply> {
+    let Some(requests) = check_requests else {
+        wait_for_interval(metrics_ticker, last_summary, interval_sec).await;
+        return Vec::new();
+    };
+    tokio::select! {
+        Some(reply) = requests.recv() => {
+            let mut replies = vec![reply];
+            // Requests queued meanwhile share the sweep
+            while let Ok(reply) = requests.try_recv() {
+                replies.push(reply);
+            }
+            info!(requests = replies.len(), "on-demand check requested");
+            replies
+        }
+        _ = wait_for_interval(metrics_ticker, last_summary, interval_sec) => Vec::new(),
+    }
+}
+
+/// Sleeps for one watch interval, logging the last summary on metrics ticks.
+async fn wait_for_interval(
+    metrics_ticker: &mut Option<tokio::time::Interval>,
+    last_summary: &Summary,
+    interval_sec: u64,
+) {
+    // Periodic metrics logging
+    if let Some(ticker) = metrics_ticker {
+        tokio::select! {
+            _ = ticker.tick() => {
+                if last_summary.availability.is_empty() {
+                    info!(total = last_summary.total, up = last_summary.up, degraded = last_summary.degraded, down = last_summary.down, "periodic summary");
+                } else {
+                    info!(total = last_summary.total, up = last_summary.up, degraded = last_summary.degraded, down = last_summary.down, availability = ?last_summary.availability, "periodic summary");
                 }
-                _ = tokio::time::sleep(Duration::from_secs(interval_sec)) => {}
             }
-        } else {
-            tokio::time::sleep(Duration::from_secs(interval_sec)).await;
+            _ = tokio::time::sleep(Duration::from_secs(interval_sec)) => {}
         }
+    } else {
+        tokio::time::sleep(Duration::from_secs(interval_sec)).await;
     }
 }
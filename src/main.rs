@@ -1,9 +1,15 @@
 use anyhow::Result;
-use clap::Parser;
-use rust_healthcheck::{Config, load_config, run_healthchecks, run_watch};
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use rust_healthcheck::{
+    Config, ConfigOverrides, HealthStatus, JsonlFile, MarkdownFile, ResultSink, ResultsFile,
+    color_enabled, emit_results, failed_endpoints, load_config_with_overrides, render_table,
+    retain_endpoints, run_sweeps, run_watch, wait_until_healthy,
+};
 use schemars::schema_for;
 use std::path::PathBuf;
-use tracing::info;
+use std::time::Duration;
+use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
 #[derive(Debug, Parser)]
@@ -19,6 +25,59 @@ struct Cli {
     /// Print JSON schema for the config and exit
     #[arg(long)]
     print_schema: bool,
+    /// Flag endpoints whose latency is an outlier relative to the rest of the sweep
+    #[arg(long)]
+    highlight_outliers: bool,
+    /// Request timeout (ms) for all endpoints, including those with their own `timeout_ms`;
+    /// from the environment it only replaces the global `request_timeout_ms`
+    #[arg(long, env = "HC_REQUEST_TIMEOUT_MS")]
+    timeout_ms: Option<u64>,
+    /// Retries for all endpoints, including those with their own `retries`; from the
+    /// environment it only replaces the global `retries`
+    #[arg(long, env = "HC_RETRIES")]
+    retries: Option<u32>,
+    /// Maximum number of concurrent checks
+    #[arg(long, env = "HC_CONCURRENCY")]
+    concurrency: Option<usize>,
+    /// Scale every request timeout, e.g. 2.0 on slow CI runners
+    #[arg(long, env = "HC_TIMEOUT_MULTIPLIER")]
+    timeout_multiplier: Option<f64>,
+    /// Poll until every endpoint is healthy, then exit 0; exit non-zero after --wait-timeout-sec
+    #[arg(long)]
+    wait_ready: bool,
+    /// Overall timeout for --wait-ready
+    #[arg(long, default_value_t = 300)]
+    wait_timeout_sec: u64,
+    /// Delay between sweeps for --wait-ready
+    #[arg(long, default_value_t = 5)]
+    wait_interval_sec: u64,
+    /// After a one-shot run, print a table of endpoints (colored on a TTY unless NO_COLOR is set)
+    #[arg(long)]
+    table: bool,
+    /// Write the detailed results of a one-shot run (summary and every outcome) as JSON
+    #[arg(long, value_name = "PATH")]
+    save_results: Option<PathBuf>,
+    /// Write a Markdown report of a one-shot run (summary, endpoint table, failures)
+    #[arg(long, value_name = "PATH")]
+    markdown: Option<PathBuf>,
+    /// Write every outcome of a one-shot run to a file as JSON Lines, one outcome per line
+    #[arg(long, value_name = "PATH")]
+    jsonl: Option<PathBuf>,
+    /// Check only the endpoints that were down in a file written by --save-results (one-shot)
+    #[arg(long, value_name = "PATH", conflicts_with = "wait_ready")]
+    replay_failed: Option<PathBuf>,
+    /// Skip `endpoints` entries that fail to parse (with a warning) instead of rejecting the config
+    #[arg(long)]
+    best_effort_config: bool,
+    /// In watch mode, exit non-zero right away if the first sweep is unhealthy
+    #[arg(long)]
+    watch_require_initial_healthy: bool,
+    /// Honor the config's `fault_injection` (for testing alerting; never use in production)
+    #[arg(long)]
+    allow_fault_injection: bool,
+    /// Patch a config value, e.g. `--set endpoints[0].timeout_ms=10000` (repeatable)
+    #[arg(long = "set", value_name = "PATH=VALUE")]
+    set: Vec<String>,
 }
 
 fn init_logging(cfg: &Config) {
@@ -47,7 +106,9 @@ fn init_logging(cfg: &Config) {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
+    let from_env = |id: &str| matches.value_source(id) == Some(ValueSource::EnvVariable);
     let config_path = cli
         .config
         .or_else(|| std::env::var_os("CONFIG_PATH").map(PathBuf::from))
@@ -57,44 +118,103 @@ async fn main() -> Result<()> {
         println!("{}", serde_json::to_string_pretty(&schema)?);
         return Ok(());
     }
-    let mut cfg: Config = load_config(&config_path)?;
-    // Basic env overrides
-    if let Ok(v) = std::env::var("CONCURRENCY")
-        && let Ok(n) = v.parse::<usize>()
-    {
-        cfg.concurrency = n;
+    let overrides = ConfigOverrides {
+        timeout_ms: cli.timeout_ms.filter(|_| !from_env("timeout_ms")),
+        retries: cli.retries.filter(|_| !from_env("retries")),
+        default_timeout_ms: cli.timeout_ms.filter(|_| from_env("timeout_ms")),
+        default_retries: cli.retries.filter(|_| from_env("retries")),
+        concurrency: cli.concurrency,
+        timeout_multiplier: cli.timeout_multiplier,
+        set: cli.set,
+        best_effort: cli.best_effort_config,
+    };
+    // Logging is configured by the config itself; surface warnings from loading it on stderr
+    let load_logger = tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .finish();
+    let mut cfg: Config = tracing::subscriber::with_default(load_logger, || {
+        let overrides = overrides.with_legacy_env(|name| std::env::var(name).ok());
+        load_config_with_overrides(&config_path, &overrides)
+    })?;
+    if cli.highlight_outliers {
+        cfg.highlight_outliers = true;
     }
-    if let Ok(v) = std::env::var("REQUEST_TIMEOUT_MS")
-        && let Ok(n) = v.parse::<u64>()
-    {
-        cfg.request_timeout_ms = n;
+    if cli.watch_require_initial_healthy {
+        cfg.watch_require_initial_healthy = true;
     }
-    if let Ok(v) = std::env::var("RETRIES")
-        && let Ok(n) = v.parse::<u32>()
-    {
-        cfg.retries = n;
+    if cli.allow_fault_injection {
+        cfg.allow_fault_injection = true;
     }
     init_logging(&cfg);
 
     info!(?config_path, "loaded configuration");
-    if cfg.watch_interval_sec.unwrap_or(0) > 0 {
+    if let Some(path) = &cli.replay_failed {
+        let failed = failed_endpoints(path)?;
+        if failed.is_empty() {
+            info!(results = ?path, "no failed endpoints to replay");
+            return Ok(());
+        }
+        for endpoint in retain_endpoints(&mut cfg, &failed) {
+            warn!(%endpoint, "previously failed endpoint is no longer configured; skipping");
+        }
+        // Replay is always a single run
+        cfg.watch_interval_sec = None;
+    }
+    if cli.wait_ready {
+        let summary = wait_until_healthy(
+            &cfg,
+            Duration::from_secs(cli.wait_timeout_sec),
+            Duration::from_secs(cli.wait_interval_sec),
+        )
+        .await?;
+        if cfg.summary_json {
+            println!("{}", serde_json::to_string(&summary.to_json())?);
+        }
+        Ok(())
+    } else if cfg.watch_interval_sec.unwrap_or(0) > 0 {
         run_watch(&cfg).await?;
         Ok(())
     } else {
-        let summary = run_healthchecks(&cfg).await?;
+        let (summary, outcomes) = run_sweeps(&cfg).await?;
+        if cli.replay_failed.is_some() {
+            for outcome in &outcomes {
+                let now = match &outcome.status {
+                    HealthStatus::Up => "up".to_string(),
+                    HealthStatus::Degraded(reason) => format!("degraded ({})", reason),
+                    HealthStatus::Down(reason) => format!("down ({})", reason),
+                };
+                println!("{}: was down, now {}", outcome.endpoint, now);
+            }
+        }
+        let mut sinks: Vec<Box<dyn ResultSink>> = Vec::new();
+        if let Some(path) = &cli.save_results {
+            sinks.push(Box::new(ResultsFile::new(path)));
+        }
+        if let Some(path) = &cli.markdown {
+            sinks.push(Box::new(MarkdownFile::new(path)));
+        }
+        if let Some(path) = &cli.jsonl {
+            sinks.push(Box::new(JsonlFile::create(path)?));
+        }
+        emit_results(&mut sinks, &summary, &outcomes)?;
+        if cli.table {
+            print!("{}", render_table(&outcomes, color_enabled()));
+        }
         if cfg.summary_json {
-            println!(
-                "{}",
-                serde_json::to_string(&serde_json::json!({
-                    "total": summary.total,
-                    "up": summary.up,
-                    "down": summary.down
-                }))?
-            );
+            println!("{}", serde_json::to_string(&summary.to_json())?);
         }
-        if summary.down > 0 {
+        if summary.has_failures(&cfg) {
             std::process::exit(1);
         }
+        if summary.timed_out > 0 {
+            warn!(
+                timed_out = summary.timed_out,
+                "endpoints timed out; tolerated by tolerate_timeouts"
+            );
+        }
+        if summary.latency_objective_met == Some(false) {
+            std::process::exit(2);
+        }
         Ok(())
     }
 }
@@ -0,0 +1,106 @@
+use crate::{CheckOutcome, HealthStatus, Summary};
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Renders a run as a Markdown report for incident channels and wikis.
+///
+/// The report has a summary line, a table of endpoints sorted by endpoint and a section per
+/// failed (degraded or down) endpoint with its reason and reason code. `generated_at` is
+/// shown in UTC.
+pub fn render_markdown(
+    summary: &Summary,
+    outcomes: &[CheckOutcome],
+    generated_at: SystemTime,
+) -> String {
+    let mut sorted: Vec<&CheckOutcome> = outcomes.iter().collect();
+    sorted.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+
+    let mut out = String::from("# Healthcheck report\n\n");
+    let _ = writeln!(out, "Generated {}\n", utc_timestamp(generated_at));
+    let _ = write!(
+        out,
+        "**Total:** {} · **Up:** {} · **Degraded:** {} · **Down:** {}",
+        summary.total, summary.up, summary.degraded, summary.down
+    );
+    if summary.timed_out > 0 {
+        let _ = write!(out, " ({} timed out)", summary.timed_out);
+    }
+    out.push_str("\n\n## Endpoints\n\n");
+    out.push_str("| Endpoint | Status | Latency | HTTP | Attempts |\n");
+    out.push_str("|---|---|---|---|---|\n");
+    for outcome in &sorted {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            cell(&outcome.endpoint),
+            status_label(&outcome.status),
+            outcome
+                .latency_ms
+                .map_or_else(|| "-".to_string(), |ms| format!("{} ms", ms)),
+            outcome
+                .last_http_status
+                .map_or_else(|| "-".to_string(), |s| s.as_u16().to_string()),
+            outcome.attempts
+        );
+    }
+
+    out.push_str("\n## Failures\n\n");
+    let failures: Vec<&&CheckOutcome> = sorted
+        .iter()
+        .filter(|o| !matches!(o.status, HealthStatus::Up))
+        .collect();
+    if failures.is_empty() {
+        out.push_str("None.\n");
+    }
+    for outcome in failures {
+        let reason = outcome.status.reason().unwrap_or_default();
+        let _ = writeln!(out, "### {}\n", outcome.endpoint);
+        let _ = writeln!(out, "- Status: {}", status_label(&outcome.status));
+        let _ = writeln!(out, "- Reason: {}", reason);
+        if let Some(code) = outcome.reason_code {
+            let _ = writeln!(out, "- Reason code: `{}`", code);
+        }
+        out.push('\n');
+    }
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    out
+}
+
+fn status_label(status: &HealthStatus) -> &'static str {
+    match status {
+        HealthStatus::Up => "up",
+        HealthStatus::Degraded(_) => "degraded",
+        HealthStatus::Down(_) => "down",
+    }
+}
+
+/// Escapes pipes so a value stays in its table cell.
+fn cell(value: &str) -> String {
+    value.replace('|', "\\|")
+}
+
+/// `YYYY-MM-DD HH:MM:SS UTC`
+fn utc_timestamp(at: SystemTime) -> String {
+    let secs = at.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
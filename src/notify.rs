@@ -0,0 +1,153 @@
+use crate::{CheckOutcome, HealthStatus};
+use anyhow::{Context, Result, anyhow};
+use reqwest::Client;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Webhook notified when an endpoint changes state in watch mode (`notify`).
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct NotifyConfig {
+    /// URL the state changes are POSTed to, e.g. a Slack incoming webhook
+    pub webhook_url: String,
+    /// Message sent as `text`, with `{endpoint}`, `{old_state}`, `{new_state}`, `{reason}` and
+    /// `{attempts}` placeholders
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+/// An endpoint moving between `up`, `degraded` and `down` from one sweep to the next.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateChange {
+    pub endpoint: String,
+    pub old_state: &'static str,
+    pub new_state: &'static str,
+    /// Failure reason of the new state; `None` when it is `up`
+    pub reason: Option<String>,
+    pub attempts: u32,
+}
+
+impl StateChange {
+    /// The webhook body; with a template, the rendered message is added as `text`.
+    pub fn payload(&self, template: Option<&str>) -> Value {
+        let mut payload = json!({
+            "endpoint": self.endpoint,
+            "old_state": self.old_state,
+            "new_state": self.new_state,
+            "reason": self.reason,
+            "attempts": self.attempts,
+        });
+        if let Some(template) = template {
+            let text = template
+                .replace("{endpoint}", &self.endpoint)
+                .replace("{old_state}", self.old_state)
+                .replace("{new_state}", self.new_state)
+                .replace("{reason}", self.reason.as_deref().unwrap_or(""))
+                .replace("{attempts}", &self.attempts.to_string());
+            payload["text"] = text.into();
+        }
+        payload
+    }
+}
+
+/// Last known state per endpoint, reporting only changes.
+///
+/// An endpoint's first outcome sets its state without a change, so a restart does not
+/// re-notify; endpoints missing from a sweep (e.g. behind an open circuit breaker) keep theirs.
+#[derive(Debug, Default)]
+pub struct StateTracker {
+    states: HashMap<String, &'static str>,
+}
+
+impl StateTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a sweep's outcomes and returns the endpoints whose state changed.
+    pub fn observe(&mut self, outcomes: &[CheckOutcome]) -> Vec<StateChange> {
+        let mut changes = Vec::new();
+        for outcome in outcomes {
+            let new_state = match &outcome.status {
+                HealthStatus::Up => "up",
+                HealthStatus::Degraded(_) => "degraded",
+                HealthStatus::Down(_) => "down",
+            };
+            let reason = outcome.status.reason();
+            match self.states.insert(outcome.endpoint.clone(), new_state) {
+                Some(old_state) if old_state != new_state => changes.push(StateChange {
+                    endpoint: outcome.endpoint.clone(),
+                    old_state,
+                    new_state,
+                    reason,
+                    attempts: outcome.attempts,
+                }),
+                _ => {}
+            }
+        }
+        changes
+    }
+}
+
+/// Posts state changes to the `notify` webhook.
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    client: Client,
+    config: NotifyConfig,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: &NotifyConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .context("building notify webhook client")?;
+        Ok(Self {
+            client,
+            config: config.clone(),
+        })
+    }
+
+    pub async fn send(&self, change: &StateChange) -> Result<()> {
+        let resp = self
+            .client
+            .post(&self.config.webhook_url)
+            .json(&change.payload(self.config.template.as_deref()))
+            .send()
+            .await
+            .context("notify webhook request failed")?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(anyhow!("notify webhook answered {}: {}", status, body));
+        }
+        Ok(())
+    }
+
+    /// Sends the change in the background so a slow or failing webhook never stalls the watch
+    /// loop; failures are logged. The handle lets a shutdown wait for delivery.
+    pub(crate) fn dispatch(self: &Arc<Self>, change: StateChange) -> tokio::task::JoinHandle<()> {
+        let notifier = Arc::clone(self);
+        tokio::spawn(async move {
+            match notifier.send(&change).await {
+                Ok(()) => debug!(
+                    endpoint = %change.endpoint,
+                    from = change.old_state,
+                    to = change.new_state,
+                    "state change notified"
+                ),
+                Err(e) => warn!(
+                    endpoint = %change.endpoint,
+                    from = change.old_state,
+                    to = change.new_state,
+                    error = %e,
+                    "state change notification failed"
+                ),
+            }
+        })
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{Config, EndpointConfig, redact_url};
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+use tracing::warn;
+
+/// Command-line patches applied to a loaded config before it is used.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    /// Request timeout for every endpoint, replacing per-endpoint `timeout_ms` too
+    pub timeout_ms: Option<u64>,
+    /// Retries for every endpoint, replacing per-endpoint `retries` too
+    pub retries: Option<u32>,
+    /// Global request timeout; endpoints keep their own `timeout_ms`
+    pub default_timeout_ms: Option<u64>,
+    /// Global retries; endpoints keep their own `retries`
+    pub default_retries: Option<u32>,
+    pub concurrency: Option<usize>,
+    pub timeout_multiplier: Option<f64>,
+    /// `path=value` assignments such as `endpoints[0].timeout_ms=10000`, applied in order
+    pub set: Vec<String>,
+    /// Skip `endpoints` entries that fail to deserialize instead of rejecting the config
+    pub best_effort: bool,
+}
+
+impl ConfigOverrides {
+    /// Falls back to the deprecated `REQUEST_TIMEOUT_MS`, `RETRIES` and `CONCURRENCY`
+    /// variables for settings neither a flag nor its `HC_*` variable provides.
+    ///
+    /// Like the `HC_*` variables they only replace the global values. Each one used logs a
+    /// deprecation warning; unparseable values are ignored with a warning.
+    pub fn with_legacy_env(mut self, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        if self.timeout_ms.is_none() && self.default_timeout_ms.is_none() {
+            self.default_timeout_ms = legacy_var(&lookup, "REQUEST_TIMEOUT_MS");
+        }
+        if self.retries.is_none() && self.default_retries.is_none() {
+            self.default_retries = legacy_var(&lookup, "RETRIES");
+        }
+        if self.concurrency.is_none() {
+            self.concurrency = legacy_var(&lookup, "CONCURRENCY");
+        }
+        self
+    }
+
+    /// Applies the global overrides to an already parsed config.
+    pub fn apply(&self, cfg: &mut Config) {
+        if let Some(n) = self.concurrency {
+            cfg.concurrency = n;
+        }
+        if let Some(ms) = self.timeout_ms.or(self.default_timeout_ms) {
+            cfg.request_timeout_ms = ms;
+        }
+        if let Some(n) = self.retries.or(self.default_retries) {
+            cfg.retries = n;
+        }
+        if self.timeout_multiplier.is_some() {
+            cfg.timeout_multiplier = self.timeout_multiplier;
+        }
+        for ep in cfg.endpoints.iter_mut().flatten() {
+            if self.timeout_ms.is_some() {
+                ep.timeout_ms = None;
+            }
+            if self.retries.is_some() {
+                ep.retries = None;
+            }
+        }
+    }
+}
+
+fn legacy_var<T: std::str::FromStr>(
+    lookup: impl Fn(&str) -> Option<String>,
+    name: &str,
+) -> Option<T> {
+    let value = lookup(name)?;
+    match value.parse() {
+        Ok(parsed) => {
+            warn!(
+                var = name,
+                "deprecated environment variable; use HC_{} instead", name
+            );
+            Some(parsed)
+        }
+        Err(_) => {
+            warn!(
+                var = name,
+                value, "ignoring unparseable deprecated environment variable"
+            );
+            None
+        }
+    }
+}
+
+/// Removes the `endpoints` entries that are not valid endpoint configs, warning about each.
+///
+/// Only the entries themselves are checked; the rest of the document must still be valid.
+pub fn drop_invalid_endpoints(root: &mut Value) {
+    let Some(Value::Array(entries)) = root.get_mut("endpoints") else {
+        return;
+    };
+    let mut index = 0;
+    entries.retain(|entry| {
+        let result = serde_json::from_value::<EndpointConfig>(entry.clone());
+        if let Err(e) = &result {
+            let url = entry
+                .get("url")
+                .and_then(Value::as_str)
+                .map_or_else(|| "<no url>".to_string(), redact_url);
+            warn!(index, url, error = %e, "skipping invalid endpoint");
+        }
+        index += 1;
+        result.is_ok()
+    });
+}
+
+/// Sets the config value at `path` from a `path=value` assignment.
+///
+/// Paths are dot-separated keys with `[i]` (or `.i`) array indices. Missing object keys are
+/// created; array indices must exist. The value is parsed as JSON if possible (`10000`,
+/// `true`, `["a"]`) and otherwise taken as a plain string.
+pub fn set_config_value(root: &mut Value, assignment: &str) -> Result<()> {
+    let (path, raw) = assignment
+        .split_once('=')
+        .with_context(|| format!("--set {}: expected PATH=VALUE", assignment))?;
+    let value = serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()));
+    let segments = parse_path(path).with_context(|| format!("--set {}: invalid path", path))?;
+    let mut current = root;
+    for segment in &segments {
+        current = match (segment, current) {
+            (Segment::Key(key), Value::Array(items)) => match key.parse::<usize>() {
+                Ok(i) => index(items, i, path)?,
+                Err(_) => bail!("--set {}: `{}` is an array, not an object", path, key),
+            },
+            (Segment::Key(key), node) => {
+                if node.is_null() {
+                    *node = Value::Object(Default::default());
+                }
+                match node {
+                    Value::Object(map) => map.entry(key.clone()).or_insert(Value::Null),
+                    _ => bail!("--set {}: cannot descend into `{}`", path, key),
+                }
+            }
+            (Segment::Index(i), Value::Array(items)) => index(items, *i, path)?,
+            (Segment::Index(i), _) => bail!("--set {}: [{}] applied to a non-array", path, i),
+        };
+    }
+    *current = value;
+    Ok(())
+}
+
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let (key, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if !key.is_empty() {
+            segments.push(Segment::Key(key.to_string()));
+        } else if rest.is_empty() {
+            bail!("empty path segment");
+        }
+        while let Some(stripped) = rest.strip_prefix('[') {
+            let (idx, tail) = stripped.split_once(']').context("unclosed `[`")?;
+            segments.push(Segment::Index(idx.parse().context("array index")?));
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            bail!("unexpected `{}`", rest);
+        }
+    }
+    Ok(segments)
+}
+
+fn index<'a>(items: &'a mut [Value], i: usize, path: &str) -> Result<&'a mut Value> {
+    let len = items.len();
+    items
+        .get_mut(i)
+        .with_context(|| format!("--set {}: index {} out of range (len {})", path, i, len))
+}
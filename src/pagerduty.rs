@@ -0,0 +1,118 @@
+use anyhow::{Context, Result, anyhow};
+use reqwest::{Client, StatusCode};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Events API v2 endpoint (US service region).
+pub const DEFAULT_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+/// Attempts per event before giving up.
+const ATTEMPTS: u32 = 3;
+const RETRY_BASE: Duration = Duration::from_millis(500);
+
+/// Incident transition for an endpoint.
+#[derive(Debug, Clone)]
+pub(crate) enum Incident {
+    Trigger { summary: String },
+    Resolve,
+}
+
+/// PagerDuty Events API v2 client keeping one incident per endpoint.
+///
+/// The endpoint (redacted URL) is the `dedup_key`, so repeated triggers for the same endpoint
+/// update a single incident, and a resolve closes it.
+#[derive(Debug, Clone)]
+pub struct PagerDuty {
+    client: Client,
+    routing_key: String,
+    events_url: String,
+}
+
+impl PagerDuty {
+    pub fn new(routing_key: &str, events_url: &str) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .context("building PagerDuty client")?;
+        Ok(Self {
+            client,
+            routing_key: routing_key.to_string(),
+            events_url: events_url.to_string(),
+        })
+    }
+
+    /// Opens (or updates) the incident for `endpoint`.
+    pub async fn trigger(&self, endpoint: &str, summary: &str) -> Result<()> {
+        self.send(json!({
+            "routing_key": self.routing_key,
+            "event_action": "trigger",
+            "dedup_key": endpoint,
+            "payload": {
+                "summary": summary,
+                "source": endpoint,
+                "severity": "critical",
+                "component": "rust-healthcheck",
+            },
+        }))
+        .await
+    }
+
+    /// Resolves the incident for `endpoint`; a no-op on PagerDuty's side if none is open.
+    pub async fn resolve(&self, endpoint: &str) -> Result<()> {
+        self.send(json!({
+            "routing_key": self.routing_key,
+            "event_action": "resolve",
+            "dedup_key": endpoint,
+        }))
+        .await
+    }
+
+    /// Sends the transition in the background so a slow or failing PagerDuty never stalls
+    /// the watch loop; failures are logged. The handle lets a shutdown wait for delivery.
+    pub(crate) fn dispatch(
+        self: &Arc<Self>,
+        endpoint: String,
+        incident: Incident,
+    ) -> tokio::task::JoinHandle<()> {
+        let pd = Arc::clone(self);
+        tokio::spawn(async move {
+            let result = match &incident {
+                Incident::Trigger { summary } => pd.trigger(&endpoint, summary).await,
+                Incident::Resolve => pd.resolve(&endpoint).await,
+            };
+            match result {
+                Ok(()) => debug!(endpoint = %endpoint, ?incident, "PagerDuty event sent"),
+                Err(e) => {
+                    warn!(endpoint = %endpoint, ?incident, error = %e, "PagerDuty event failed")
+                }
+            }
+        })
+    }
+
+    async fn send(&self, event: Value) -> Result<()> {
+        let mut attempt = 1;
+        loop {
+            let err = match self.client.post(&self.events_url).json(&event).send().await {
+                Ok(resp) if resp.status().is_success() => return Ok(()),
+                Ok(resp) => {
+                    let status = resp.status();
+                    let body = resp.text().await.unwrap_or_default();
+                    // A rejected event (bad routing key, malformed payload) will not succeed later
+                    if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                        return Err(anyhow!("PagerDuty rejected event ({}): {}", status, body));
+                    }
+                    anyhow!("PagerDuty answered {}: {}", status, body)
+                }
+                Err(e) => anyhow!(e).context("PagerDuty request failed"),
+            };
+            if attempt >= ATTEMPTS {
+                return Err(err.context(format!("giving up after {} attempts", attempt)));
+            }
+            debug!(attempt, error = %err, "retrying PagerDuty event");
+            tokio::time::sleep(RETRY_BASE * 2u32.pow(attempt - 1)).await;
+            attempt += 1;
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use metrics::gauge;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tracing::info;
+
+/// Check counts of a running sweep, logged periodically so large sweeps are not silent.
+#[derive(Debug)]
+pub(crate) struct SweepProgress {
+    total: usize,
+    in_flight: AtomicUsize,
+    done: AtomicUsize,
+}
+
+impl SweepProgress {
+    pub(crate) fn new(total: usize) -> Self {
+        Self {
+            total,
+            in_flight: AtomicUsize::new(0),
+            done: AtomicUsize::new(0),
+        }
+    }
+
+    /// Marks a check as in flight until the returned guard is dropped.
+    pub(crate) fn start(&self) -> InFlight<'_> {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        gauge!("healthcheck_inflight").increment(1.0);
+        InFlight { progress: self }
+    }
+
+    fn log(&self) {
+        let done = self.done.load(Ordering::Relaxed);
+        let in_flight = self.in_flight.load(Ordering::Relaxed);
+        let pending = self.total.saturating_sub(done + in_flight);
+        info!(
+            "checked {}/{}, {} in flight, {} pending",
+            done, self.total, in_flight, pending
+        );
+    }
+
+    /// Awaits `sweep`, logging progress every `every` (never if `None`) until it completes.
+    pub(crate) async fn report_while<F: Future>(
+        &self,
+        every: Option<Duration>,
+        sweep: F,
+    ) -> F::Output {
+        let Some(every) = every else {
+            return sweep.await;
+        };
+        tokio::pin!(sweep);
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + every, every);
+        loop {
+            tokio::select! {
+                out = &mut sweep => return out,
+                _ = ticker.tick() => self.log(),
+            }
+        }
+    }
+}
+
+/// A check counted as in flight; counted as done once dropped.
+pub(crate) struct InFlight<'a> {
+    progress: &'a SweepProgress,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.progress.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.progress.done.fetch_add(1, Ordering::Relaxed);
+        gauge!("healthcheck_inflight").decrement(1.0);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{Config, http_responder, latency_histogram_buckets};
+use anyhow::{Context, Result, anyhow, bail};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+
+/// How often histogram samples are folded into the exported buckets between scrapes
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The recorder this module installed and its `latency_buckets`, kept so a later exporter
+/// serves the same metrics.
+static INSTALLED: OnceLock<(PrometheusHandle, Option<Vec<f64>>)> = OnceLock::new();
+
+/// Serves the check metrics in the Prometheus text format at `/metrics` on
+/// `metrics_listen_addr` (watch mode).
+///
+/// Starting it installs the process-wide `metrics` recorder, with the configured
+/// `latency_buckets`, which later exporters in the process must then share; a process that
+/// installed its own recorder cannot use it. The listener stops when the exporter is dropped.
+#[derive(Debug)]
+pub struct MetricsExporter {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl MetricsExporter {
+    pub async fn start(cfg: &Config, addr: &str) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("failed to bind metrics listener on {}", addr))?;
+        let local = listener.local_addr()?;
+        let handle = install_recorder(cfg)?;
+        info!(addr = %local, "metrics endpoint listening");
+        let task = tokio::spawn(async move {
+            let mut upkeep = tokio::time::interval(UPKEEP_INTERVAL);
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => {
+                            let handle = handle.clone();
+                            tokio::spawn(async move {
+                                if let Err(e) = serve(stream, &handle).await {
+                                    debug!(error = %e, "metrics connection error");
+                                }
+                            });
+                        }
+                        Err(e) => debug!(error = %e, "metrics accept failed"),
+                    },
+                    _ = upkeep.tick() => handle.run_upkeep(),
+                }
+            }
+        });
+        Ok(Self { addr: local, task })
+    }
+
+    /// The address the listener is bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for MetricsExporter {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn install_recorder(cfg: &Config) -> Result<PrometheusHandle> {
+    if let Some((handle, buckets)) = INSTALLED.get() {
+        // Bucket boundaries are fixed when the recorder is built
+        if buckets.as_deref() != cfg.latency_buckets.as_deref() {
+            bail!(
+                "latency_buckets cannot change once the metrics recorder is installed \
+                 (installed with {:?})",
+                buckets
+            );
+        }
+        return Ok(handle.clone());
+    }
+    let mut builder = PrometheusBuilder::new();
+    if let Some((name, buckets)) = latency_histogram_buckets(cfg) {
+        builder = builder
+            .set_buckets_for_metric(Matcher::Full(name.to_string()), buckets)
+            .context("invalid latency_buckets")?;
+    }
+    let recorder = builder.build_recorder();
+    let handle = recorder.handle();
+    metrics::set_global_recorder(recorder).map_err(|_| {
+        anyhow!("metrics_listen_addr: another metrics recorder is already installed")
+    })?;
+    let (handle, _) = INSTALLED.get_or_init(|| (handle, cfg.latency_buckets.clone()));
+    Ok(handle.clone())
+}
+
+async fn serve(mut stream: TcpStream, handle: &PrometheusHandle) -> std::io::Result<()> {
+    let (status, content_type, body) = match http_responder::read_request(&mut stream).await? {
+        (method, path) if method == "GET" && path == "/metrics" => {
+            ("200 OK", "text/plain; version=0.0.4", handle.render())
+        }
+        _ => ("404 Not Found", "text/plain", "not found".to_string()),
+    };
+    http_responder::respond(&mut stream, status, content_type, &body).await
+}
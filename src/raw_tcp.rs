@@ -0,0 +1,152 @@
+use crate::reason::{io_code, io_reason};
+use crate::{CheckOutcome, EndpointConfig, RawTcpExpect, ReasonCode};
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use url::Url;
+
+const DEFAULT_MAX_BYTES: usize = 4096;
+/// Characters of the response quoted in a mismatch reason
+const PREVIEW_CHARS: usize = 64;
+
+/// Checks a `kind: raw_tcp` endpoint when the config is loaded, returning its compiled
+/// `raw_tcp_expect.regex`.
+pub(crate) fn validate(ep: &EndpointConfig) -> Result<Option<Regex>> {
+    let Some(expect) = &ep.raw_tcp_expect else {
+        bail!("`kind: raw_tcp` requires a `raw_tcp_expect`");
+    };
+    if expect.contains.is_none() && expect.regex.is_none() {
+        bail!("`raw_tcp_expect` needs `contains` and/or `regex`");
+    }
+    let regex = match &expect.regex {
+        Some(pattern) => Some(
+            Regex::new(pattern)
+                .with_context(|| format!("invalid raw_tcp_expect regex {}", pattern))?,
+        ),
+        None => None,
+    };
+    validate_address(&ep.url)?;
+    Ok(regex)
+}
+
+/// Checks that a `kind: tcp` or `raw_tcp` URL names a host and port.
+pub(crate) fn validate_address(url: &str) -> Result<()> {
+    address(url).map_err(anyhow::Error::msg)?;
+    Ok(())
+}
+
+/// Connects to `target` and closes the connection; a `kind: tcp` check.
+///
+/// Latency is the time to establish the connection.
+pub(crate) async fn connect(
+    target: &str,
+    timeout: Duration,
+    outcome: &mut CheckOutcome,
+) -> Result<(), (ReasonCode, String)> {
+    let (host, port) = address(target).map_err(|reason| (ReasonCode::ConfigError, reason))?;
+    let start = Instant::now();
+    match tokio::time::timeout(timeout, TcpStream::connect((host.as_str(), port))).await {
+        Ok(Ok(_)) => {
+            outcome.latency_ms = Some(start.elapsed().as_millis());
+            Ok(())
+        }
+        Ok(Err(e)) => Err(io_error(e)),
+        Err(_) => Err((ReasonCode::Timeout, "timeout".to_string())),
+    }
+}
+
+/// Connects to `target`, sends `send` and reads until the response matches.
+///
+/// Reading stops at a match, after `max_bytes`, when the server closes the connection or at
+/// `timeout`. Latency is the time to the match.
+pub(crate) async fn check(
+    ep: &EndpointConfig,
+    target: &str,
+    timeout: Duration,
+    outcome: &mut CheckOutcome,
+) -> Result<(), (ReasonCode, String)> {
+    let config_error = |reason: String| (ReasonCode::ConfigError, reason);
+    let Some(expect) = &ep.raw_tcp_expect else {
+        return Err(config_error(
+            "raw_tcp check without a `raw_tcp_expect`".to_string(),
+        ));
+    };
+    let (host, port) = address(target).map_err(config_error)?;
+    let start = Instant::now();
+    let regex = ep.raw_tcp_regex.as_ref();
+    match tokio::time::timeout(timeout, exchange(&host, port, expect, regex)).await {
+        Ok(Ok(())) => {
+            outcome.latency_ms = Some(start.elapsed().as_millis());
+            Ok(())
+        }
+        Ok(Err(reason)) => Err(reason),
+        Err(_) => Err((ReasonCode::Timeout, "timeout".to_string())),
+    }
+}
+
+async fn exchange(
+    host: &str,
+    port: u16,
+    expect: &RawTcpExpect,
+    regex: Option<&Regex>,
+) -> Result<(), (ReasonCode, String)> {
+    let mismatch = |reason: String| (ReasonCode::ResponseMismatch, reason);
+    let mut stream = TcpStream::connect((host, port)).await.map_err(io_error)?;
+    if let Some(send) = &expect.send {
+        stream.write_all(send.as_bytes()).await.map_err(io_error)?;
+    }
+    let max_bytes = expect.max_bytes.unwrap_or(DEFAULT_MAX_BYTES);
+    let mut received = Vec::new();
+    let mut buf = [0u8; 1024];
+    loop {
+        let text = String::from_utf8_lossy(&received);
+        let matched = expect
+            .contains
+            .as_ref()
+            .is_none_or(|c| text.contains(c.as_str()))
+            && regex.is_none_or(|r| r.is_match(&text));
+        if matched {
+            return Ok(());
+        }
+        if received.len() >= max_bytes {
+            return Err(mismatch(format!(
+                "no match in the first {} bytes (got {:?})",
+                max_bytes,
+                preview(&text)
+            )));
+        }
+        let n = stream.read(&mut buf).await.map_err(io_error)?;
+        if n == 0 {
+            return Err(mismatch(format!(
+                "connection closed without a match (got {:?})",
+                preview(&text)
+            )));
+        }
+        received.extend_from_slice(&buf[..n.min(max_bytes - received.len())]);
+    }
+}
+
+fn io_error(e: std::io::Error) -> (ReasonCode, String) {
+    let reason = io_reason(e.kind()).map_or_else(|| e.to_string(), str::to_string);
+    (io_code(e.kind()), reason)
+}
+
+/// Host and port of a `tcp://host:port` (or http/https) URL.
+fn address(target: &str) -> Result<(String, u16), String> {
+    let url = Url::parse(target).map_err(|e| format!("invalid url: {}", e))?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| "url has no host".to_string())?
+        .trim_matches(['[', ']'])
+        .to_string();
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| "url has no port".to_string())?;
+    Ok((host, port))
+}
+
+fn preview(text: &str) -> String {
+    text.chars().take(PREVIEW_CHARS).collect()
+}
@@ -0,0 +1,302 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+
+/// Stable, machine-readable class of a failed check, alongside its human-readable reason.
+///
+/// Reported in JSON output and as a metric label as e.g. `TIMEOUT`; unlike the reason text,
+/// the codes do not change with message wording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReasonCode {
+    Timeout,
+    ConnectRefused,
+    /// Connect error, reset or connection closed early
+    ConnectionFailed,
+    DnsFailure,
+    TlsError,
+    /// Redirect error, https→http downgrade or unexpected final URL
+    Redirect,
+    HttpStatus,
+    /// Forbidden response header present
+    HeaderMismatch,
+    /// Body size, emptiness, schema or golden file mismatch
+    BodyMismatch,
+    SoftError,
+    /// Response slower than `max_latency_ms` or `max_ttfb_ms`
+    SlaViolation,
+    CertExpired,
+    /// Certificate fails `cert_requirements` other than by expiry
+    CertPolicy,
+    /// Certificate chain not trusted, with `fail_on_untrusted_cert`
+    CertUntrusted,
+    /// `kind: dns` answers differ from the expectation
+    DnsMismatch,
+    /// `raw_tcp_expect` did not match
+    ResponseMismatch,
+    /// Response served over another HTTP version than `http_version`
+    ProtocolMismatch,
+    InjectedFault,
+    /// The endpoint cannot be checked as configured
+    ConfigError,
+    RequestFailed,
+}
+
+impl ReasonCode {
+    pub fn as_str(self) -> &'static str {
+        use ReasonCode::*;
+        match self {
+            Timeout => "TIMEOUT",
+            ConnectRefused => "CONNECT_REFUSED",
+            ConnectionFailed => "CONNECTION_FAILED",
+            DnsFailure => "DNS_FAILURE",
+            TlsError => "TLS_ERROR",
+            Redirect => "REDIRECT",
+            HttpStatus => "HTTP_STATUS",
+            HeaderMismatch => "HEADER_MISMATCH",
+            BodyMismatch => "BODY_MISMATCH",
+            SoftError => "SOFT_ERROR",
+            SlaViolation => "SLA_VIOLATION",
+            CertExpired => "CERT_EXPIRED",
+            CertPolicy => "CERT_POLICY",
+            CertUntrusted => "CERT_UNTRUSTED",
+            DnsMismatch => "DNS_MISMATCH",
+            ResponseMismatch => "RESPONSE_MISMATCH",
+            ProtocolMismatch => "PROTOCOL_MISMATCH",
+            InjectedFault => "INJECTED_FAULT",
+            ConfigError => "CONFIG_ERROR",
+            RequestFailed => "REQUEST_FAILED",
+        }
+    }
+
+    /// Coarse failure class, the `reason` label of `healthcheck_down_total`: `timeout`,
+    /// `connect`, `http`, `body` or `other`.
+    pub fn category(self) -> &'static str {
+        use ReasonCode::*;
+        match self {
+            Timeout => "timeout",
+            ConnectRefused | ConnectionFailed | DnsFailure | TlsError => "connect",
+            Redirect | HttpStatus | HeaderMismatch | ProtocolMismatch => "http",
+            BodyMismatch | SoftError | ResponseMismatch => "body",
+            SlaViolation | CertExpired | CertPolicy | CertUntrusted | DnsMismatch
+            | InjectedFault | ConfigError | RequestFailed => "other",
+        }
+    }
+}
+
+impl fmt::Display for ReasonCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Why an endpoint is down. `Display` gives the reason as reported, e.g. `timeout` or
+/// `HTTP 503 Service Unavailable`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownReason {
+    Timeout,
+    /// Connection refused, reset or closed early, or a DNS or TLS failure
+    Connect(String),
+    /// Status not matching `expected_status`
+    Http(reqwest::StatusCode),
+    /// Body failed an assertion (size, content, schema, golden file, ...)
+    BodyMismatch(String),
+    /// Response slower than `max_latency_ms`
+    LatencyExceeded {
+        latency_ms: u128,
+        max_ms: u64,
+    },
+    /// A reason with the `on_failure_probe` diagnosis appended
+    Diagnosed {
+        reason: Box<DownReason>,
+        diagnosis: String,
+    },
+    Other(String),
+}
+
+impl DownReason {
+    /// Classifies a reported reason by its code; the text is kept as is.
+    pub(crate) fn new(code: ReasonCode, reason: String) -> Self {
+        use ReasonCode::*;
+        match code {
+            Timeout if reason == "timeout" => DownReason::Timeout,
+            ConnectRefused | ConnectionFailed | DnsFailure | TlsError => {
+                DownReason::Connect(reason)
+            }
+            BodyMismatch => DownReason::BodyMismatch(reason),
+            _ => DownReason::Other(reason),
+        }
+    }
+}
+
+impl fmt::Display for DownReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownReason::Timeout => f.write_str("timeout"),
+            DownReason::Http(status) => write!(f, "HTTP {}", status),
+            DownReason::LatencyExceeded { latency_ms, max_ms } => {
+                write!(f, "latency {}ms > {}ms", latency_ms, max_ms)
+            }
+            DownReason::Diagnosed { reason, diagnosis } => write!(f, "{}; {}", reason, diagnosis),
+            DownReason::Connect(reason)
+            | DownReason::BodyMismatch(reason)
+            | DownReason::Other(reason) => f.write_str(reason),
+        }
+    }
+}
+
+/// Maps a request error to a stable, canonical failure reason.
+///
+/// reqwest's message embeds the URL, and its source chain carries addresses, ephemeral ports
+/// and OS error codes, so the same failure produces different strings from one check to the
+/// next. The canonical reason (`connection refused`, `timeout`, `dns resolution failed`, ...)
+/// is suitable as an alert or grouping key; [`error_chain`] keeps the full detail.
+pub fn normalize_reason(err: &reqwest::Error) -> String {
+    if err.is_timeout() {
+        return "timeout".to_string();
+    }
+    if let Some(missing) = sources(err).find(|e| e.is::<crate::timing::MissingSniAlias>()) {
+        return missing.to_string();
+    }
+    if let Some(reason) = sources(err)
+        .filter_map(|e| e.downcast_ref::<io::Error>())
+        .find_map(|e| io_reason(e.kind()))
+    {
+        return reason.to_string();
+    }
+    let chain = error_chain(err).to_ascii_lowercase();
+    if chain.contains("dns error") || chain.contains("failed to lookup address") {
+        return "dns resolution failed".to_string();
+    }
+    if err.is_redirect() {
+        return "redirect error".to_string();
+    }
+    // The innermost error is the most specific, e.g. `invalid peer certificate: UnknownIssuer`
+    let innermost = sources(err)
+        .last()
+        .map_or_else(String::new, |e| e.to_string());
+    match sanitize(&innermost) {
+        reason if reason.is_empty() && err.is_connect() => "connect error".to_string(),
+        reason if reason.is_empty() => "request failed".to_string(),
+        reason => reason,
+    }
+}
+
+/// The [`ReasonCode`] of a request error, following the same precedence as [`normalize_reason`].
+pub(crate) fn reason_code(err: &reqwest::Error) -> ReasonCode {
+    if err.is_timeout() {
+        return ReasonCode::Timeout;
+    }
+    if sources(err).any(|e| e.is::<crate::timing::MissingSniAlias>()) {
+        return ReasonCode::ConfigError;
+    }
+    if let Some(code) = sources(err)
+        .filter_map(|e| e.downcast_ref::<io::Error>())
+        .find_map(|e| io_reason(e.kind()).map(|_| io_code(e.kind())))
+    {
+        return code;
+    }
+    let chain = error_chain(err).to_ascii_lowercase();
+    if chain.contains("dns error") || chain.contains("failed to lookup address") {
+        return ReasonCode::DnsFailure;
+    }
+    if err.is_redirect() {
+        return ReasonCode::Redirect;
+    }
+    if chain.contains("certificate") || chain.contains("tls") {
+        return ReasonCode::TlsError;
+    }
+    if err.is_connect() {
+        return ReasonCode::ConnectionFailed;
+    }
+    ReasonCode::RequestFailed
+}
+
+/// Whether the request failed at the connection level (connect error, reset or a peer
+/// hanging up before answering), as opposed to a timeout or an HTTP-level problem.
+pub(crate) fn is_connection_failure(err: &reqwest::Error) -> bool {
+    if err.is_timeout() {
+        return false;
+    }
+    if err.is_connect() {
+        return true;
+    }
+    let io_failure = sources(err)
+        .filter_map(|e| e.downcast_ref::<io::Error>())
+        .any(|e| {
+            matches!(
+                e.kind(),
+                io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::UnexpectedEof
+            )
+        });
+    // hyper reports a peer closing mid-exchange without an io error
+    io_failure
+        || error_chain(err)
+            .to_ascii_lowercase()
+            .contains("connection closed before message completed")
+}
+
+/// The error and all of its sources, joined with `: `.
+pub fn error_chain(err: &(dyn Error + 'static)) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for e in sources(err) {
+        let message = e.to_string();
+        // Some wrappers repeat their source's message
+        if parts.last() != Some(&message) {
+            parts.push(message);
+        }
+    }
+    parts.join(": ")
+}
+
+fn sources<'a>(err: &'a (dyn Error + 'static)) -> impl Iterator<Item = &'a (dyn Error + 'static)> {
+    std::iter::successors(Some(err), |&e| e.source())
+}
+
+pub(crate) fn io_reason(kind: io::ErrorKind) -> Option<&'static str> {
+    use io::ErrorKind::*;
+    Some(match kind {
+        ConnectionRefused => "connection refused",
+        ConnectionReset => "connection reset",
+        ConnectionAborted => "connection aborted",
+        UnexpectedEof => "connection closed",
+        TimedOut => "timeout",
+        AddrNotAvailable => "address not available",
+        NetworkUnreachable => "network unreachable",
+        HostUnreachable => "host unreachable",
+        _ => return None,
+    })
+}
+
+/// The [`ReasonCode`] of an io error kind (see [`io_reason`]).
+pub(crate) fn io_code(kind: io::ErrorKind) -> ReasonCode {
+    match kind {
+        io::ErrorKind::TimedOut => ReasonCode::Timeout,
+        io::ErrorKind::ConnectionRefused => ReasonCode::ConnectRefused,
+        _ => ReasonCode::ConnectionFailed,
+    }
+}
+
+/// Drops OS error codes and replaces addresses and URLs with placeholders.
+fn sanitize(message: &str) -> String {
+    let message = match message.find(" (os error ") {
+        Some(i) => &message[..i],
+        None => message,
+    };
+    message
+        .split(' ')
+        .map(|word| {
+            let token = word.trim_matches(|c: char| "(),;\"'".contains(c));
+            if token.starts_with("http://") || token.starts_with("https://") {
+                word.replace(token, "<url>")
+            } else if token.parse::<SocketAddr>().is_ok() || token.parse::<IpAddr>().is_ok() {
+                word.replace(token, "<addr>")
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
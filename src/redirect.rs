@@ -0,0 +1,47 @@
+use reqwest::redirect::{Attempt, Policy};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+/// Redirects followed before giving up, as in reqwest's default policy.
+const MAX_REDIRECTS: usize = 10;
+
+tokio::task_local! {
+    static CHAIN: Arc<Mutex<Vec<Url>>>;
+}
+
+/// reqwest's default redirect behavior, additionally recording the chain for [`recorded`].
+///
+/// The policy is consulted while the request future is polled, so the chain is attributed to
+/// the check awaiting it.
+pub(crate) fn policy() -> Policy {
+    Policy::custom(|attempt: Attempt| {
+        let _ = CHAIN.try_with(|chain| {
+            let mut chain = chain.lock().expect("redirect chain lock poisoned");
+            chain.clear();
+            chain.extend(attempt.previous().iter().cloned());
+            chain.push(attempt.url().clone());
+        });
+        if attempt.previous().len() > MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// Runs `fut` and returns the URLs it went through, starting with the requested one; empty if
+/// it was not redirected.
+pub(crate) async fn recorded<F: Future>(fut: F) -> (F::Output, Vec<Url>) {
+    let chain = Arc::new(Mutex::new(Vec::new()));
+    let out = CHAIN.scope(Arc::clone(&chain), fut).await;
+    let chain = std::mem::take(&mut *chain.lock().expect("redirect chain lock poisoned"));
+    (out, chain)
+}
+
+/// Whether any hop of `chain` goes from `https` to plain `http`.
+pub(crate) fn downgrades(chain: &[Url]) -> bool {
+    chain
+        .windows(2)
+        .any(|hop| hop[0].scheme() == "https" && hop[1].scheme() == "http")
+}
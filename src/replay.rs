@@ -0,0 +1,67 @@
+use crate::{CheckOutcome, Config, Summary, redact_url};
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Detailed results of a run, as written by `--save-results`.
+pub fn results_json(summary: &Summary, outcomes: &[CheckOutcome]) -> Value {
+    serde_json::json!({
+        "summary": summary.to_json(),
+        "outcomes": outcomes.iter().map(CheckOutcome::to_json).collect::<Vec<_>>(),
+    })
+}
+
+/// Endpoints that were down in a saved results file.
+///
+/// Accepts the `--save-results` document or a bare array of outcomes.
+pub fn failed_endpoints(path: &Path) -> Result<Vec<String>> {
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("failed to read results file {}", path.display()))?;
+    let doc: Value = serde_json::from_str(&raw)
+        .with_context(|| format!("{} is not valid JSON", path.display()))?;
+    let outcomes = match doc.get("outcomes").unwrap_or(&doc) {
+        Value::Array(outcomes) => outcomes,
+        _ => bail!("{} has no `outcomes` array", path.display()),
+    };
+    let mut failed = Vec::new();
+    for outcome in outcomes {
+        if outcome["status"] != "down" {
+            continue;
+        }
+        match outcome["endpoint"].as_str() {
+            Some(endpoint) => failed.push(endpoint.to_string()),
+            None => bail!("{}: outcome without an `endpoint`", path.display()),
+        }
+    }
+    Ok(failed)
+}
+
+/// Restricts `cfg` to the endpoints in `endpoints`, matched by their (redacted) URL, so their
+/// full settings are recovered from the current config.
+///
+/// Discovery and the endpoint count expectation are turned off, as they describe the full
+/// inventory. Returns the entries that match no configured endpoint.
+pub fn retain_endpoints(cfg: &mut Config, endpoints: &[String]) -> Vec<String> {
+    let wanted = |url: &str| endpoints.iter().any(|e| *e == redact_url(url));
+    if let Some(configured) = &mut cfg.endpoints {
+        configured.retain(|ep| wanted(&ep.url));
+    } else {
+        cfg.endpoints_to_check.retain(|url| wanted(url));
+    }
+    cfg.k8s_discovery = None;
+    cfg.expected_endpoint_count = None;
+    let kept: Vec<String> = match &cfg.endpoints {
+        Some(configured) => configured.iter().map(|ep| redact_url(&ep.url)).collect(),
+        None => cfg
+            .endpoints_to_check
+            .iter()
+            .map(|u| redact_url(u))
+            .collect(),
+    };
+    endpoints
+        .iter()
+        .filter(|e| !kept.contains(e))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,137 @@
+use crate::http_responder;
+use anyhow::{Context, Result};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info};
+
+/// `POST /check` requests waiting for the watch loop
+const CHECK_REQUEST_QUEUE: usize = 16;
+
+/// Answer to a `POST /check` request: whether the sweep was healthy, and its summary JSON.
+pub type CheckReply = oneshot::Sender<(bool, serde_json::Value)>;
+
+/// Liveness/readiness state of the checker process itself.
+///
+/// `/livez` always answers 200 while the process is serving. `/readyz` answers 200 only if a
+/// sweep completed within `stale_after`, so a wedged watch loop turns the probe unhealthy.
+/// With on-demand checks enabled, `POST /check` runs a sweep right away and answers with its
+/// summary.
+#[derive(Debug)]
+pub struct SelfHealth {
+    started: Instant,
+    /// Milliseconds since `started` at which the last sweep finished, plus one (0 = never)
+    last_sweep_ms: AtomicU64,
+    stale_after: Duration,
+    check_requests: Option<mpsc::Sender<CheckReply>>,
+}
+
+impl SelfHealth {
+    pub fn new(stale_after: Duration) -> Self {
+        Self {
+            started: Instant::now(),
+            last_sweep_ms: AtomicU64::new(0),
+            stale_after,
+            check_requests: None,
+        }
+    }
+
+    /// Enables `POST /check`; the returned receiver yields one reply channel per request, to
+    /// be answered once a sweep started after the request has completed.
+    pub fn enable_on_demand_checks(&mut self) -> mpsc::Receiver<CheckReply> {
+        let (tx, rx) = mpsc::channel(CHECK_REQUEST_QUEUE);
+        self.check_requests = Some(tx);
+        rx
+    }
+
+    /// Records that a sweep has just completed.
+    pub fn record_sweep(&self) {
+        let ms = self.started.elapsed().as_millis() as u64;
+        self.last_sweep_ms
+            .store(ms.saturating_add(1), Ordering::Relaxed);
+    }
+
+    /// Time since the last completed sweep, if any.
+    pub fn since_last_sweep(&self) -> Option<Duration> {
+        match self.last_sweep_ms.load(Ordering::Relaxed) {
+            0 => None,
+            stamp => {
+                let at = Duration::from_millis(stamp - 1);
+                Some(self.started.elapsed().saturating_sub(at))
+            }
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.since_last_sweep()
+            .is_some_and(|age| age <= self.stale_after)
+    }
+
+    /// Binds `addr` and serves `/livez`, `/readyz` and `/check` in a background task.
+    pub async fn serve(self: Arc<Self>, addr: &str) -> Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("failed to bind self-health listener on {}", addr))?;
+        let local = listener.local_addr()?;
+        info!(addr = %local, "self-health endpoint listening");
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let state = Arc::clone(&self);
+                        tokio::spawn(async move {
+                            if let Err(e) = state.handle(stream).await {
+                                debug!(error = %e, "self-health connection error");
+                            }
+                        });
+                    }
+                    Err(e) => debug!(error = %e, "self-health accept failed"),
+                }
+            }
+        });
+        Ok(local)
+    }
+
+    async fn handle(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let (method, path) = http_responder::read_request(&mut stream).await?;
+        let mut content_type = "text/plain";
+        let (status, body) = match (method.as_str(), path.as_str()) {
+            ("GET", "/livez") => ("200 OK", "ok".to_string()),
+            ("POST", "/check") if self.check_requests.is_some() => {
+                content_type = "application/json";
+                match self.on_demand_check().await {
+                    Some((true, summary)) => ("200 OK", summary.to_string()),
+                    Some((false, summary)) => ("503 Service Unavailable", summary.to_string()),
+                    None => {
+                        content_type = "text/plain";
+                        ("503 Service Unavailable", "watch loop stopped".to_string())
+                    }
+                }
+            }
+            ("GET", "/readyz") => match self.since_last_sweep() {
+                Some(age) if age <= self.stale_after => ("200 OK", "ready".to_string()),
+                Some(age) => (
+                    "503 Service Unavailable",
+                    format!("stale: last sweep completed {}s ago", age.as_secs()),
+                ),
+                None => (
+                    "503 Service Unavailable",
+                    "no sweep completed yet".to_string(),
+                ),
+            },
+            _ => ("404 Not Found", "not found".to_string()),
+        };
+        http_responder::respond(&mut stream, status, content_type, &body).await
+    }
+
+    /// Queues a sweep request with the watch loop and waits for its result.
+    async fn on_demand_check(&self) -> Option<(bool, serde_json::Value)> {
+        let requests = self.check_requests.as_ref()?;
+        let (reply, result) = oneshot::channel();
+        requests.send(reply).await.ok()?;
+        result.await.ok()
+    }
+}
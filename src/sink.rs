@@ -0,0 +1,118 @@
+use crate::{CheckOutcome, Summary, render_markdown, results_json};
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Destination for the results of a run.
+///
+/// Receives the summary and every outcome once the run is over. Implement it to send results
+/// somewhere the built-in sinks do not cover.
+pub trait ResultSink: Send {
+    /// Writes a finished run's summary and outcomes.
+    fn write(&mut self, summary: &Summary, outcomes: &[CheckOutcome]) -> Result<()>;
+}
+
+/// Hands a run's summary and outcomes (in the given order) to every sink.
+///
+/// Every sink is written even if an earlier one fails; the first error is returned.
+pub fn emit_results(
+    sinks: &mut [Box<dyn ResultSink>],
+    summary: &Summary,
+    outcomes: &[CheckOutcome],
+) -> Result<()> {
+    let mut result = Ok(());
+    for sink in sinks.iter_mut() {
+        let written = sink.write(summary, outcomes);
+        if result.is_ok() {
+            result = written;
+        }
+    }
+    result
+}
+
+/// Prints the summary and every outcome to stdout as one JSON document (the `--save-results`
+/// format).
+#[derive(Debug, Default)]
+pub struct StdoutJson;
+
+impl ResultSink for StdoutJson {
+    fn write(&mut self, summary: &Summary, outcomes: &[CheckOutcome]) -> Result<()> {
+        println!(
+            "{}",
+            serde_json::to_string(&results_json(summary, outcomes))?
+        );
+        Ok(())
+    }
+}
+
+/// Writes one JSON outcome per line to a file.
+#[derive(Debug)]
+pub struct JsonlFile {
+    path: PathBuf,
+    out: BufWriter<File>,
+}
+
+impl JsonlFile {
+    /// Creates (or truncates) the file at `path`.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file =
+            File::create(&path).with_context(|| format!("failed to create {}", path.display()))?;
+        Ok(Self {
+            path,
+            out: BufWriter::new(file),
+        })
+    }
+}
+
+impl ResultSink for JsonlFile {
+    fn write(&mut self, _summary: &Summary, outcomes: &[CheckOutcome]) -> Result<()> {
+        outcomes
+            .iter()
+            .try_for_each(|outcome| writeln!(self.out, "{}", outcome.to_json()))
+            .and_then(|()| self.out.flush())
+            .with_context(|| format!("failed to write {}", self.path.display()))
+    }
+}
+
+/// Writes the summary and every outcome as pretty-printed JSON (`--save-results`).
+#[derive(Debug)]
+pub struct ResultsFile {
+    path: PathBuf,
+}
+
+impl ResultsFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl ResultSink for ResultsFile {
+    fn write(&mut self, summary: &Summary, outcomes: &[CheckOutcome]) -> Result<()> {
+        let results = serde_json::to_string_pretty(&results_json(summary, outcomes))?;
+        std::fs::write(&self.path, results)
+            .with_context(|| format!("failed to write {}", self.path.display()))
+    }
+}
+
+/// Writes a Markdown report (`--markdown`, see [`render_markdown`]).
+#[derive(Debug)]
+pub struct MarkdownFile {
+    path: PathBuf,
+}
+
+impl MarkdownFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl ResultSink for MarkdownFile {
+    fn write(&mut self, summary: &Summary, outcomes: &[CheckOutcome]) -> Result<()> {
+        let report = render_markdown(summary, outcomes, SystemTime::now());
+        std::fs::write(&self.path, report)
+            .with_context(|| format!("failed to write {}", self.path.display()))
+    }
+}
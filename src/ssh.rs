@@ -0,0 +1,107 @@
+use crate::SshJump;
+use anyhow::{Context, Result, bail};
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tracing::{debug, info};
+
+/// Port forwards through a jump host, backed by an `ssh -N -L ...` child process.
+///
+/// The process is killed when the tunnel is dropped.
+#[derive(Debug)]
+pub struct SshTunnel {
+    child: Child,
+}
+
+impl SshTunnel {
+    /// Starts `ssh` and waits until every local forward accepts connections.
+    pub async fn open(cfg: &SshJump) -> Result<Self> {
+        let child = Command::new(&cfg.command)
+            .args(ssh_args(cfg))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("ssh_jump: failed to start `{}`", cfg.command))?;
+        let mut tunnel = Self { child };
+        let deadline = Instant::now() + Duration::from_millis(cfg.connect_timeout_ms);
+        for fwd in &cfg.forwards {
+            loop {
+                if let Some(status) = tunnel.child.try_wait()? {
+                    let mut stderr = String::new();
+                    if let Some(pipe) = tunnel.child.stderr.as_mut() {
+                        let _ = pipe.read_to_string(&mut stderr);
+                    }
+                    bail!(
+                        "ssh_jump: tunnel to {} exited ({}): {}",
+                        cfg.host,
+                        status,
+                        stderr.trim()
+                    );
+                }
+                if TcpStream::connect(("127.0.0.1", fwd.local_port))
+                    .await
+                    .is_ok()
+                {
+                    break;
+                }
+                if Instant::now() >= deadline {
+                    bail!(
+                        "ssh_jump: local port {} not forwarded within {}ms",
+                        fwd.local_port,
+                        cfg.connect_timeout_ms
+                    );
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }
+        info!(host = %cfg.host, forwards = cfg.forwards.len(), "ssh tunnel established");
+        Ok(tunnel)
+    }
+
+    /// Process id of the `ssh` child.
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+}
+
+impl Drop for SshTunnel {
+    fn drop(&mut self) {
+        debug!(pid = self.child.id(), "closing ssh tunnel");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Arguments passed to `ssh` for the given jump configuration.
+pub fn ssh_args(cfg: &SshJump) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "-N",
+        "-o",
+        "BatchMode=yes",
+        "-o",
+        "ExitOnForwardFailure=yes",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    if let Some(port) = cfg.port {
+        args.extend(["-p".to_string(), port.to_string()]);
+    }
+    if let Some(key) = &cfg.key_path {
+        args.extend(["-i".to_string(), key.clone()]);
+    }
+    for fwd in &cfg.forwards {
+        args.push("-L".to_string());
+        args.push(format!(
+            "127.0.0.1:{}:{}:{}",
+            fwd.local_port, fwd.remote_host, fwd.remote_port
+        ));
+    }
+    args.push(match &cfg.user {
+        Some(user) => format!("{}@{}", user, cfg.host),
+        None => cfg.host.clone(),
+    });
+    args
+}
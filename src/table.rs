@@ -0,0 +1,94 @@
+use crate::{CheckOutcome, HealthStatus};
+use std::io::IsTerminal;
+
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+const HEADERS: [&str; 6] = [
+    "ENDPOINT", "STATUS", "LATENCY", "ATTEMPTS", "HTTP", "REASON",
+];
+
+/// Whether terminal output should be colored: stdout is a TTY and `NO_COLOR` is unset or empty.
+pub fn color_enabled() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+}
+
+/// Renders outcomes as an aligned table, one row per endpoint sorted by endpoint.
+///
+/// With `color`, the status cell is green (up), yellow (degraded) or red (down).
+pub fn render_table(outcomes: &[CheckOutcome], color: bool) -> String {
+    let mut sorted: Vec<&CheckOutcome> = outcomes.iter().collect();
+    sorted.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+    let rows: Vec<[String; 6]> = sorted.iter().map(|o| row(o)).collect();
+    let mut widths = HEADERS.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    push_line(&mut out, &HEADERS.map(String::from), &widths, None);
+    for (row, outcome) in rows.iter().zip(&sorted) {
+        let status_color = color.then_some(match outcome.status {
+            HealthStatus::Up => GREEN,
+            HealthStatus::Degraded(_) => YELLOW,
+            HealthStatus::Down(_) => RED,
+        });
+        push_line(&mut out, row, &widths, status_color);
+    }
+    out
+}
+
+fn row(outcome: &CheckOutcome) -> [String; 6] {
+    let status = match &outcome.status {
+        HealthStatus::Up => "up",
+        HealthStatus::Degraded(_) => "degraded",
+        HealthStatus::Down(_) => "down",
+    };
+    let reason = outcome.status.reason().unwrap_or_default();
+    [
+        outcome.endpoint.clone(),
+        status.to_string(),
+        outcome
+            .latency_ms
+            .map_or_else(|| "-".to_string(), |ms| format!("{} ms", ms)),
+        outcome.attempts.to_string(),
+        outcome
+            .last_http_status
+            .map_or_else(|| "-".to_string(), |s| s.as_u16().to_string()),
+        reason.to_string(),
+    ]
+}
+
+/// Appends one line; the status cell (second column) is wrapped in `status_color` if given.
+fn push_line(
+    out: &mut String,
+    cells: &[String; 6],
+    widths: &[usize; 6],
+    status_color: Option<&str>,
+) {
+    let last = cells.len() - 1;
+    let mut line = String::new();
+    for (i, (cell, width)) in cells.iter().zip(widths).enumerate() {
+        // No trailing padding on the last column
+        let padded = if i == last {
+            cell.clone()
+        } else {
+            format!("{:<width$}  ", cell, width = width)
+        };
+        match status_color {
+            Some(code) if i == 1 => {
+                line.push_str(code);
+                line.push_str(cell);
+                line.push_str(RESET);
+                line.push_str(&padded[cell.len()..]);
+            }
+            _ => line.push_str(&padded),
+        }
+    }
+    out.push_str(line.trim_end());
+    out.push('\n');
+}
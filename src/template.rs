@@ -0,0 +1,253 @@
+use crate::{
+    BodySizeRange, CertRequirements, EndpointAuth, EndpointConfig, ExpectMetric, ExpectedStatus,
+    FailureProbe, HttpVersion, redact_url,
+};
+use anyhow::{Result, bail};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// Reusable endpoint settings referenced by name from `endpoints[].template`.
+///
+/// Every field is optional; an endpoint inherits the fields it does not set itself. Fields
+/// that identify a target (`url`, `kind`, `failover_urls`, `sni`, ...) and on/off switches
+/// such as `detect_soft_errors` are not inheritable, and setting them here is an error.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EndpointTemplate {
+    /// Parent template whose fields this one inherits
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Inherited only by endpoints that leave `method` at the default (GET)
+    #[serde(default)]
+    pub method: Option<String>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub retries: Option<u32>,
+    #[serde(default)]
+    pub expected_status: Option<ExpectedStatus>,
+    /// Merged with the endpoint's headers; the endpoint wins on conflicts
+    #[serde(default)]
+    pub headers: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub auth: Option<EndpointAuth>,
+    /// `body`/`body_file` are inherited together, only if the endpoint sets neither
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub body_file: Option<String>,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub on_failure_probe: Option<FailureProbe>,
+    #[serde(default)]
+    pub max_ttfb_ms: Option<u64>,
+    #[serde(default)]
+    pub capture_response_header: Option<String>,
+    #[serde(default)]
+    pub base_backoff_ms: Option<u64>,
+    #[serde(default)]
+    pub max_backoff_ms: Option<u64>,
+    #[serde(default)]
+    pub connection_retries: Option<u32>,
+    #[serde(default)]
+    pub retry_timeout_multiplier: Option<f64>,
+    #[serde(default)]
+    pub retry_timeout_max_ms: Option<u64>,
+    #[serde(default)]
+    pub forbidden_headers: Option<Vec<String>>,
+    #[serde(default)]
+    pub apdex_target_ms: Option<u64>,
+    #[serde(default)]
+    pub soft_error_markers: Option<Vec<String>>,
+    #[serde(default)]
+    pub max_latency_ms: Option<u64>,
+    #[serde(default)]
+    pub expected_body_size: Option<BodySizeRange>,
+    #[serde(default)]
+    pub expected_body_schema: Option<String>,
+    #[serde(default)]
+    pub expect_metric: Option<ExpectMetric>,
+    #[serde(default)]
+    pub expect_charset: Option<String>,
+    #[serde(default)]
+    pub body_contains: Option<String>,
+    #[serde(default)]
+    pub body_matches: Option<String>,
+    #[serde(default)]
+    pub max_body_bytes: Option<usize>,
+    #[serde(default)]
+    pub report_degraded: Option<bool>,
+    #[serde(default)]
+    pub connection_close: Option<bool>,
+    #[serde(default)]
+    pub cert_requirements: Option<CertRequirements>,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub accept: Option<String>,
+    #[serde(default)]
+    pub local_address: Option<IpAddr>,
+    #[serde(default)]
+    pub http_version: Option<HttpVersion>,
+}
+
+impl EndpointTemplate {
+    /// Fills fields unset in `self` from `base`.
+    fn inherit(&mut self, base: &EndpointTemplate) {
+        fill(&mut self.method, &base.method);
+        fill(&mut self.timeout_ms, &base.timeout_ms);
+        fill(&mut self.retries, &base.retries);
+        fill(&mut self.expected_status, &base.expected_status);
+        merge_headers(&mut self.headers, &base.headers);
+        fill(&mut self.auth, &base.auth);
+        if self.body.is_none() && self.body_file.is_none() {
+            self.body = base.body.clone();
+            self.body_file = base.body_file.clone();
+        }
+        fill(&mut self.content_type, &base.content_type);
+        fill(&mut self.on_failure_probe, &base.on_failure_probe);
+        fill(&mut self.max_ttfb_ms, &base.max_ttfb_ms);
+        fill(
+            &mut self.capture_response_header,
+            &base.capture_response_header,
+        );
+        fill(&mut self.base_backoff_ms, &base.base_backoff_ms);
+        fill(&mut self.max_backoff_ms, &base.max_backoff_ms);
+        fill(&mut self.connection_retries, &base.connection_retries);
+        fill(
+            &mut self.retry_timeout_multiplier,
+            &base.retry_timeout_multiplier,
+        );
+        fill(&mut self.retry_timeout_max_ms, &base.retry_timeout_max_ms);
+        fill(&mut self.forbidden_headers, &base.forbidden_headers);
+        fill(&mut self.apdex_target_ms, &base.apdex_target_ms);
+        fill(&mut self.soft_error_markers, &base.soft_error_markers);
+        fill(&mut self.max_latency_ms, &base.max_latency_ms);
+        fill(&mut self.expected_body_size, &base.expected_body_size);
+        fill(&mut self.expected_body_schema, &base.expected_body_schema);
+        fill(&mut self.expect_metric, &base.expect_metric);
+        fill(&mut self.expect_charset, &base.expect_charset);
+        fill(&mut self.body_contains, &base.body_contains);
+        fill(&mut self.body_matches, &base.body_matches);
+        fill(&mut self.max_body_bytes, &base.max_body_bytes);
+        fill(&mut self.report_degraded, &base.report_degraded);
+        fill(&mut self.connection_close, &base.connection_close);
+        fill(&mut self.cert_requirements, &base.cert_requirements);
+        fill(&mut self.user_agent, &base.user_agent);
+        fill(&mut self.accept, &base.accept);
+        fill(&mut self.local_address, &base.local_address);
+        fill(&mut self.http_version, &base.http_version);
+    }
+
+    fn apply_to(&self, ep: &mut EndpointConfig) {
+        if let Some(method) = &self.method
+            && ep.method == "GET"
+        {
+            ep.method = method.clone();
+        }
+        fill(&mut ep.timeout_ms, &self.timeout_ms);
+        fill(&mut ep.retries, &self.retries);
+        fill(&mut ep.expected_status, &self.expected_status);
+        merge_headers(&mut ep.headers, &self.headers);
+        fill(&mut ep.auth, &self.auth);
+        if ep.body.is_none() && ep.body_file.is_none() {
+            ep.body = self.body.clone();
+            ep.body_file = self.body_file.clone();
+        }
+        fill(&mut ep.content_type, &self.content_type);
+        fill(&mut ep.on_failure_probe, &self.on_failure_probe);
+        fill(&mut ep.max_ttfb_ms, &self.max_ttfb_ms);
+        fill(
+            &mut ep.capture_response_header,
+            &self.capture_response_header,
+        );
+        fill(&mut ep.base_backoff_ms, &self.base_backoff_ms);
+        fill(&mut ep.max_backoff_ms, &self.max_backoff_ms);
+        fill(&mut ep.connection_retries, &self.connection_retries);
+        fill(
+            &mut ep.retry_timeout_multiplier,
+            &self.retry_timeout_multiplier,
+        );
+        fill(&mut ep.retry_timeout_max_ms, &self.retry_timeout_max_ms);
+        fill(&mut ep.forbidden_headers, &self.forbidden_headers);
+        fill(&mut ep.apdex_target_ms, &self.apdex_target_ms);
+        fill(&mut ep.soft_error_markers, &self.soft_error_markers);
+        fill(&mut ep.max_latency_ms, &self.max_latency_ms);
+        fill(&mut ep.expected_body_size, &self.expected_body_size);
+        fill(&mut ep.expected_body_schema, &self.expected_body_schema);
+        fill(&mut ep.expect_metric, &self.expect_metric);
+        fill(&mut ep.expect_charset, &self.expect_charset);
+        fill(&mut ep.body_contains, &self.body_contains);
+        fill(&mut ep.body_matches, &self.body_matches);
+        fill(&mut ep.max_body_bytes, &self.max_body_bytes);
+        fill(&mut ep.report_degraded, &self.report_degraded);
+        fill(&mut ep.connection_close, &self.connection_close);
+        fill(&mut ep.cert_requirements, &self.cert_requirements);
+        fill(&mut ep.user_agent, &self.user_agent);
+        fill(&mut ep.accept, &self.accept);
+        fill(&mut ep.local_address, &self.local_address);
+        fill(&mut ep.http_version, &self.http_version);
+    }
+}
+
+fn fill<T: Clone>(field: &mut Option<T>, inherited: &Option<T>) {
+    if field.is_none() {
+        *field = inherited.clone();
+    }
+}
+
+fn merge_headers(
+    own: &mut Option<HashMap<String, String>>,
+    inherited: &Option<HashMap<String, String>>,
+) {
+    let Some(inherited) = inherited else { return };
+    let headers = own.get_or_insert_with(HashMap::new);
+    for (k, v) in inherited {
+        if !headers
+            .keys()
+            .any(|own_key| own_key.eq_ignore_ascii_case(k))
+        {
+            headers.insert(k.clone(), v.clone());
+        }
+    }
+}
+
+/// Applies each endpoint's `template` (and its parents) to the endpoint.
+pub(crate) fn apply_templates(
+    endpoints: &mut [EndpointConfig],
+    templates: &HashMap<String, EndpointTemplate>,
+) -> Result<()> {
+    for ep in endpoints {
+        if let Some(name) = &ep.template {
+            let resolved = resolve(name, templates, &mut Vec::new())
+                .map_err(|e| e.context(format!("endpoint {}", redact_url(&ep.url))))?;
+            resolved.apply_to(ep);
+        }
+    }
+    Ok(())
+}
+
+/// Flattens the template chain starting at `name`; `chain` holds the names being resolved.
+fn resolve<'a>(
+    name: &'a str,
+    templates: &'a HashMap<String, EndpointTemplate>,
+    chain: &mut Vec<&'a str>,
+) -> Result<EndpointTemplate> {
+    if chain.contains(&name) {
+        chain.push(name);
+        bail!("template cycle: {}", chain.join(" -> "));
+    }
+    let Some(template) = templates.get(name) else {
+        bail!("unknown template `{}`", name);
+    };
+    chain.push(name);
+    let mut resolved = template.clone();
+    if let Some(parent) = &template.template {
+        resolved.inherit(&resolve(parent, templates, chain)?);
+    }
+    chain.pop();
+    Ok(resolved)
+}
@@ -0,0 +1,166 @@
+use crate::DnsCache;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Connection phase timings for a single check, similar to `curl -w`.
+///
+/// Phases are only observed when the request opened a new connection: for a pooled
+/// connection, or for an IP-literal host (no lookup), the corresponding fields are `None`.
+/// rustls does not expose the boundary between the TCP connect and the TLS handshake, so for
+/// HTTPS targets `connect_ms` covers both. Time to first byte and total time are on
+/// [`CheckOutcome`](crate::CheckOutcome) as `ttfb_ms` and `total_ms`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimingBreakdown {
+    /// Name resolution
+    pub dns_ms: Option<u128>,
+    /// TCP connect, plus the TLS handshake for HTTPS
+    pub connect_ms: Option<u128>,
+}
+
+/// Raw phase durations recorded by the resolver and connector hooks.
+#[derive(Debug, Default)]
+struct Phases {
+    dns: Option<Duration>,
+    /// Whole connector call, including name resolution
+    connect: Option<Duration>,
+}
+
+tokio::task_local! {
+    static PHASES: Arc<Mutex<Phases>>;
+    /// Server name of the `sni` request being sent
+    static SNI: String;
+}
+
+/// Lookup of an `sni` server name by a client that was not built for the endpoint, which
+/// would otherwise connect wherever the name resolves instead of to the URL host.
+#[derive(Debug)]
+pub(crate) struct MissingSniAlias(String);
+
+impl std::fmt::Display for MissingSniAlias {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "client has no alias for sni {}; build it with build_endpoint_client",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for MissingSniAlias {}
+
+/// Runs `fut`, the request of an endpoint with server name `sni`: a client without that
+/// alias fails the lookup with [`MissingSniAlias`] rather than resolving the server name.
+pub(crate) async fn with_sni<F: Future>(sni: Option<&str>, fut: F) -> F::Output {
+    match sni {
+        Some(sni) => SNI.scope(sni.to_string(), fut).await,
+        None => fut.await,
+    }
+}
+
+fn record(f: impl FnOnce(&mut Phases)) {
+    // Outside `timed` (e.g. a connect finishing in the background after the request picked a
+    // pooled connection) there is nothing to attribute the timing to.
+    let _ = PHASES.try_with(|phases| f(&mut phases.lock().expect("timing lock poisoned")));
+}
+
+/// Runs `fut` and returns the connection phases observed while it was polled.
+pub(crate) async fn timed<F: Future>(fut: F) -> (F::Output, TimingBreakdown) {
+    let phases = Arc::new(Mutex::new(Phases::default()));
+    let out = PHASES.scope(Arc::clone(&phases), fut).await;
+    let phases = phases.lock().expect("timing lock poisoned");
+    let breakdown = TimingBreakdown {
+        dns_ms: phases.dns.map(|d| d.as_millis()),
+        connect_ms: phases
+            .connect
+            .map(|c| c.saturating_sub(phases.dns.unwrap_or_default()).as_millis()),
+    };
+    (out, breakdown)
+}
+
+/// System resolver (getaddrinfo on the blocking pool, like reqwest's default) that times lookups.
+///
+/// With a [`DnsCache`], failed lookups fall back to the host's last resolved addresses.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TimedResolver {
+    pub(crate) cache: Option<Arc<DnsCache>>,
+    /// Name looked up as another host: an `sni` endpoint's server name and its URL host
+    pub(crate) alias: Option<(String, String)>,
+}
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let cache = self.cache.clone();
+        let host = match &self.alias {
+            Some((from, to)) if from.as_str() == name.as_str() => to.clone(),
+            _ if SNI.try_with(|sni| sni == name.as_str()).unwrap_or(false) => {
+                let missing = MissingSniAlias(name.as_str().to_string());
+                return Box::pin(async move { Err(missing.into()) });
+            }
+            _ => name.as_str().to_string(),
+        };
+        Box::pin(async move {
+            let start = Instant::now();
+            let lookup = async {
+                tokio::net::lookup_host((host.as_str(), 0))
+                    .await
+                    .map(|addrs| addrs.collect::<Vec<SocketAddr>>())
+            };
+            let result = match &cache {
+                Some(cache) => cache.resolve(&host, lookup).await,
+                None => lookup.await,
+            };
+            let elapsed = start.elapsed();
+            record(|p| p.dns = Some(elapsed));
+            Ok(Box::new(result?.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Connector layer timing each new connection (resolve + connect + TLS handshake).
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ConnectTimingLayer;
+
+impl<S> Layer<S> for ConnectTimingLayer {
+    type Service = ConnectTiming<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectTiming { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectTiming<S> {
+    inner: S,
+}
+
+impl<S, R> Service<R> for ConnectTiming<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let start = Instant::now();
+            let conn = fut.await?;
+            let elapsed = start.elapsed();
+            record(|p| p.connect = Some(elapsed));
+            Ok(conn)
+        })
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{
+    CheckOutcome, Config, DownReason, EndpointConfig, ExpectedStatus, HealthStatus, ReasonCode,
+    build_client, check_endpoint_once, check_with_retries, http_method, redact_url,
+};
+use std::time::Duration;
+
+/// Checks `url` with the default settings (GET, 2xx expected, config-file default timeout).
+///
+/// Builds a throwaway client for the call; for repeated checks build one with
+/// [`build_client`] and use [`check_endpoint_once`], or see [`UrlCheck`] for options.
+pub async fn check_url(url: &str) -> CheckOutcome {
+    UrlCheck::new(url).run().await
+}
+
+/// Options for checking a single URL without a config file.
+///
+/// Unset options keep the [`EndpointConfig`] defaults.
+#[derive(Debug, Clone)]
+pub struct UrlCheck {
+    endpoint: EndpointConfig,
+    retries: u32,
+}
+
+impl UrlCheck {
+    pub fn new(url: &str) -> Self {
+        Self {
+            endpoint: EndpointConfig {
+                url: url.to_string(),
+                ..Default::default()
+            },
+            retries: 0,
+        }
+    }
+
+    /// HTTP method (`GET`, `HEAD`, `POST`, `PUT`, `PATCH`, `DELETE` or `OPTIONS`)
+    pub fn method(mut self, method: &str) -> Self {
+        self.endpoint.method = method.to_ascii_uppercase();
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.endpoint
+            .headers
+            .get_or_insert_default()
+            .insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<String>) -> Self {
+        self.endpoint.body = Some(body.into());
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.endpoint.timeout_ms = Some(timeout.as_millis() as u64);
+        self
+    }
+
+    pub fn expected_status(mut self, expected: ExpectedStatus) -> Self {
+        self.endpoint.expected_status = Some(expected);
+        self
+    }
+
+    /// Retries with the default backoff after a failed attempt
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Runs the check with a throwaway client.
+    pub async fn run(self) -> CheckOutcome {
+        let config_error = |reason: String| {
+            let mut outcome = CheckOutcome::new(
+                redact_url(&self.endpoint.url),
+                HealthStatus::Down(DownReason::Other(reason)),
+            );
+            outcome.reason_code = Some(ReasonCode::ConfigError);
+            outcome
+        };
+        if http_method(&self.endpoint.method).is_none() {
+            return config_error(format!("unsupported method `{}`", self.endpoint.method));
+        }
+        let cfg = default_config();
+        let client = match build_client(&cfg) {
+            Ok(client) => client,
+            Err(e) => return config_error(format!("{:#}", e)),
+        };
+        if self.retries == 0 {
+            return check_endpoint_once(&client, &self.endpoint, cfg.request_timeout_ms).await;
+        }
+        check_with_retries(
+            &client,
+            &self.endpoint,
+            self.retries,
+            cfg.request_timeout_ms,
+            cfg.base_backoff_ms,
+            cfg.max_backoff_ms,
+        )
+        .await
+    }
+}
+
+/// The config a file with no endpoints would produce, i.e. every documented default.
+fn default_config() -> Config {
+    serde_json::from_value(serde_json::json!({ "endpoints_to_check": [] }))
+        .expect("an empty config is valid")
+}
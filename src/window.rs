@@ -0,0 +1,46 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Time-bounded window of samples; entries older than `max_age` are pruned on access.
+#[derive(Debug, Clone)]
+pub struct RollingWindow<T> {
+    max_age: Duration,
+    samples: VecDeque<(Instant, T)>,
+}
+
+impl<T> RollingWindow<T> {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, now: Instant, value: T) {
+        self.samples.push_back((now, value));
+        self.prune(now);
+    }
+
+    /// Drops samples older than `max_age` relative to `now`.
+    pub fn prune(&mut self, now: Instant) {
+        while let Some((at, _)) = self.samples.front() {
+            if now.saturating_duration_since(*at) > self.max_age {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.samples.iter().map(|(_, v)| v)
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}